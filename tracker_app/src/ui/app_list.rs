@@ -1,16 +1,27 @@
+use std::{error::Error, fs::File, io::BufWriter};
+
+use chrono::{Datelike, Days, Local, NaiveDate};
 use eframe::{
     egui::{Context, Label, Layout, RichText, ScrollArea, Separator, Ui},
     emath::Align,
 };
-use tracker_core::store::{
-    apps_store::{use_apps_store, Actions},
-    user_store::use_user_store,
+use tracker_core::{
+    store::{
+        apps_store::{use_apps_store, Actions},
+        user_store::{use_user_store, UserActions},
+    },
+    tracking::{
+        export::{default_export_path, export, ExportFormat, ExportOptions, ExportRows},
+        limits::{LimitAlert, LimitLevel, UsageLimits},
+        usage::{usage_by_period, Bucket},
+        TrackLog, TrackingMode,
+    },
 };
 
 use super::{
-    basics::{core_btn, input_field},
+    basics::{core_btn, input_field, text_small_button},
     configs::{ACCENT, ADDITIONAL_2, ERROR_COLOR, HEADING_COLOR, SUB_HEADING_COLOR},
-    modals::{change_proc_name_modal, confirm_modal},
+    modals::{change_proc_name_modal, confirm_modal, limits_modal},
     utils::format_time,
 };
 
@@ -20,18 +31,28 @@ const PADDING: f32 = 5.0;
 
 pub struct AppListItem {
     pub name: String,
-    pub uptime: *const u64,
+    pub uptime: u64,
+    pub today: u64,
+    pub this_week: u64,
+    pub mode: TrackingMode,
     pub display_name: String,
-    is_running: *const bool,
+    is_running: bool,
     on_edit_modal_open: bool,
     new_display_name: String,
+    limits: UsageLimits,
+    on_limits_modal_open: bool,
+    daily_limit_input: String,
+    weekly_limit_input: String,
 }
 
 impl AppListItem {
-    pub fn new(name: &str, uptime: &u64, display_name: &str, is_running: &bool) -> Self {
+    pub fn new(name: &str, uptime: u64, display_name: &str, is_running: &bool) -> Self {
         Self {
             name: String::from(name),
             uptime,
+            today: 0,
+            this_week: 0,
+            mode: TrackingMode::default(),
             on_edit_modal_open: false,
             display_name: if display_name.trim() == "" {
                 String::from(name)
@@ -39,7 +60,11 @@ impl AppListItem {
                 String::from(display_name)
             },
             new_display_name: display_name.to_owned(),
-            is_running,
+            is_running: *is_running,
+            limits: UsageLimits::default(),
+            on_limits_modal_open: false,
+            daily_limit_input: String::new(),
+            weekly_limit_input: String::new(),
         }
     }
 
@@ -49,14 +74,27 @@ impl AppListItem {
             ui.with_layout(Layout::top_down(Align::Min), |ui| {
                 ui.colored_label(HEADING_COLOR, format!("App: {}", &self.display_name));
                 ui.colored_label(
-                    unsafe {
-                        if *self.is_running {
-                            ACCENT
-                        } else {
-                            SUB_HEADING_COLOR
+                    if self.is_running {
+                        ACCENT
+                    } else {
+                        SUB_HEADING_COLOR
+                    },
+                    match self.mode {
+                        TrackingMode::Uptime => format!("Used for: {}", format_time(self.uptime)),
+                        TrackingMode::Focused => {
+                            format!("Focused for: {}", format_time(self.uptime))
                         }
                     },
-                    format!("Used for: {}", format_time(unsafe { *self.uptime })),
+                );
+                ui.colored_label(
+                    SUB_HEADING_COLOR,
+                    format!(
+                        "Today: {}{} | This week: {}{}",
+                        format_time(self.today),
+                        limit_text(self.limits.daily),
+                        format_time(self.this_week),
+                        limit_text(self.limits.weekly)
+                    ),
                 );
             })
         });
@@ -67,6 +105,12 @@ impl AppListItem {
             let edit_btn = core_btn(ui, ADDITIONAL_2, "EDIT")
                 .on_hover_cursor(eframe::egui::CursorIcon::PointingHand);
 
+            let limits_btn = core_btn(ui, ADDITIONAL_2, "LIMITS");
+
+            if limits_btn.clicked() {
+                self.on_limits_modal_open = !self.on_limits_modal_open;
+            }
+
             if edit_btn.clicked() {
                 self.on_edit_modal_open = !self.on_edit_modal_open;
             }
@@ -80,8 +124,60 @@ impl AppListItem {
             self.render_edit_modal(ui);
         }
 
+        if self.on_limits_modal_open {
+            self.render_limits_modal(ui);
+        }
+
         ui.add_space(PADDING);
     }
+    fn sync(&mut self, log: &TrackLog, mode: TrackingMode) {
+        self.mode = mode;
+        self.is_running = log.is_running;
+        self.limits = log.limits;
+        if !self.on_limits_modal_open {
+            let minutes =
+                |limit: Option<u64>| limit.map_or(String::new(), |s| (s / 60).to_string());
+            self.daily_limit_input = minutes(log.limits.daily);
+            self.weekly_limit_input = minutes(log.limits.weekly);
+        }
+        self.uptime = log.time(mode);
+        let today = Local::now().date_naive();
+        let monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+        let usage = usage_by_period(std::slice::from_ref(log), Bucket::Day, monday, today, mode);
+
+        if let Some(usage) = usage.first() {
+            self.today = usage.buckets.last().map_or(0, |b| b.seconds);
+            self.this_week = usage.total();
+        }
+    }
+
+    fn render_limits_modal(&mut self, ui: &mut Ui) {
+        let proc_name = self.name.to_owned();
+        let modal_ptr: *mut bool = &mut self.on_limits_modal_open;
+
+        limits_modal(
+            ui.ctx(),
+            &mut self.daily_limit_input,
+            &mut self.weekly_limit_input,
+            |daily, weekly| {
+                /* Keep the modal open until both are numbers or empty */
+                let (Some(daily), Some(weekly)) = (parse_limit(daily), parse_limit(weekly)) else {
+                    return;
+                };
+                use_apps_store()
+                    .lock()
+                    .unwrap()
+                    .dispatch(Actions::SetLimits(proc_name, UsageLimits { daily, weekly }));
+                unsafe {
+                    modal_ptr.write(false);
+                }
+            },
+            || unsafe {
+                modal_ptr.write(false);
+            },
+        )
+    }
+
     fn render_edit_modal(&mut self, ui: &mut Ui) {
         let proc_name = self.name.to_owned();
         let display_name = self.display_name.to_owned();
@@ -101,12 +197,35 @@ impl AppListItem {
         })
     }
 }
+/// Limit in seconds from minutes the user typed. Empty or 0 is no limit, `None` if not a number
+fn parse_limit(minutes: &str) -> Option<Option<u64>> {
+    match minutes.trim() {
+        "" => Some(None),
+        minutes => minutes
+            .parse::<u64>()
+            .ok()
+            .map(|m| (m > 0).then_some(m * 60)),
+    }
+}
+
+fn limit_text(limit: Option<u64>) -> String {
+    limit.map_or(String::new(), |secs| format!(" of {}", format_time(secs)))
+}
+
 /// Apps that our application is tracking. Added by user.
 pub struct AppList {
     list: Vec<AppListItem>,
     on_delete_modal_open: bool,
     app_to_delete: String,
     app_to_delete_display_name: String,
+    /// Days back from today. Whole history if not set
+    export_range: Option<u64>,
+    export_format: ExportFormat,
+    export_rows: ExportRows,
+    /// Written file or the error
+    export_result: Option<Result<String, String>>,
+    /// Store revision, mode and day the usage of the items was worked out for
+    synced: Option<(u64, TrackingMode, NaiveDate)>,
 }
 
 impl AppList {
@@ -116,12 +235,20 @@ impl AppList {
             on_delete_modal_open: false,
             app_to_delete: String::new(),
             app_to_delete_display_name: String::new(),
+            export_range: Some(7),
+            export_format: ExportFormat::Csv,
+            export_rows: ExportRows::Sessions,
+            export_result: None,
+            synced: None,
         }
     }
 
     pub fn render(&mut self, ui: &mut Ui) {
         ui.add_space(PADDING);
         ui.vertical_centered(|ui| ui.heading("Applications you use"));
+        self.render_mode_switch(ui);
+        self.render_export(ui);
+        self.render_limit_alerts(ui);
         ui.add(Separator::default().spacing(20.0));
 
         self.make_list();
@@ -147,26 +274,170 @@ impl AppList {
         ui.add_space(PADDING);
     }
     fn make_list(&mut self) {
-        if use_apps_store()
+        let mode = use_user_store().selector().tracking_mode;
+        let store = use_apps_store();
+        let mut store = store.lock().unwrap();
+        let state = store.selector();
+
+        /* Usage is derived from sessions, so it's worked out again only when they may have changed */
+        let synced = Some((state.revision, mode, Local::now().date_naive()));
+        if synced == self.synced {
+            return;
+        }
+        self.synced = synced;
+
+        let is_same_apps = self
+            .list
+            .iter()
+            .map(|item| &item.name)
+            .eq(state.tracked_apps.iter().map(|log| &log.process_name));
+        if !is_same_apps {
+            self.list = state
+                .tracked_apps
+                .iter()
+                .map(|log| {
+                    // Keeps what's being edited in the item of the same app
+                    match self
+                        .list
+                        .iter()
+                        .position(|item| item.name == log.process_name)
+                    {
+                        Some(i) => self.list.swap_remove(i),
+                        None => AppListItem::new(
+                            &log.process_name,
+                            log.uptime(),
+                            &log.display_name,
+                            &log.is_running,
+                        ),
+                    }
+                })
+                .collect();
+        }
+        for item in &mut self.list {
+            if let Some(log) = state
+                .tracked_apps
+                .iter()
+                .find(|log| log.process_name == item.name)
+            {
+                item.sync(log, mode);
+            }
+        }
+    }
+
+    /// Lets user choose which time is shown and used for badges
+    fn render_mode_switch(&self, ui: &mut Ui) {
+        let mode = use_user_store().selector().tracking_mode;
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Count:").color(SUB_HEADING_COLOR));
+            for (option, text) in [
+                (TrackingMode::Uptime, "Uptime"),
+                (TrackingMode::Focused, "Focused time"),
+            ] {
+                if ui.selectable_label(mode == option, text).clicked() && mode != option {
+                    use_user_store().dispatch(UserActions::SetTrackingMode(option));
+                }
+            }
+        });
+    }
+
+    /// Banners of limits reached until the user dismisses them
+    fn render_limit_alerts(&self, ui: &mut Ui) {
+        let alerts: Vec<LimitAlert> = use_apps_store()
             .lock()
             .unwrap()
             .selector()
-            .tracked_apps
-            .len()
-            != self.list.len()
-        {
-            self.list = vec![];
-            for item in &use_apps_store().lock().unwrap().selector().tracked_apps {
-                self.list.push(AppListItem::new(
-                    &item.process_name,
-                    &item.uptime,
-                    &item.display_name,
-                    &item.is_running,
-                ))
+            .limit_alerts
+            .iter()
+            .filter(|a| !a.dismissed)
+            .cloned()
+            .collect();
+        if alerts.is_empty() {
+            return;
+        }
+
+        ui.add_space(PADDING);
+        for alert in &alerts {
+            let color = match alert.level {
+                LimitLevel::Warning => HEADING_COLOR,
+                LimitLevel::Reached => ERROR_COLOR,
+            };
+            ui.colored_label(color, alert.message());
+        }
+        text_small_button(ui, "Dismiss", None, || {
+            use_apps_store()
+                .lock()
+                .unwrap()
+                .dispatch(Actions::DismissLimitAlerts)
+        });
+    }
+
+    /// Writes usage history of the chosen range to the downloads directory
+    fn render_export(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Export:").color(SUB_HEADING_COLOR));
+            for (option, text) in [(Some(7), "7 days"), (Some(30), "30 days"), (None, "All")] {
+                if ui
+                    .selectable_label(self.export_range == option, text)
+                    .clicked()
+                {
+                    self.export_range = option;
+                }
+            }
+            ui.separator();
+            for (option, text) in [
+                (ExportRows::Sessions, "Sessions"),
+                (ExportRows::Days, "Days"),
+            ] {
+                if ui
+                    .selectable_label(self.export_rows == option, text)
+                    .clicked()
+                {
+                    self.export_rows = option;
+                }
+            }
+            ui.separator();
+            for (option, text) in [(ExportFormat::Csv, "CSV"), (ExportFormat::Ndjson, "JSON")] {
+                if ui
+                    .selectable_label(self.export_format == option, text)
+                    .clicked()
+                {
+                    self.export_format = option;
+                }
+            }
+            if core_btn(ui, ADDITIONAL_2, "EXPORT").clicked() {
+                self.export_result = Some(self.export().map_err(|e| e.to_string()));
+            }
+        });
+
+        match &self.export_result {
+            Some(Ok(path)) => {
+                ui.colored_label(SUB_HEADING_COLOR, format!("Saved to {}", path));
             }
+            Some(Err(e)) => {
+                ui.colored_label(ERROR_COLOR, format!("Export failed: {}", e));
+            }
+            None => (),
         }
     }
 
+    fn export(&self) -> Result<String, Box<dyn Error>> {
+        let mode = use_user_store().selector().tracking_mode;
+        let mut options = ExportOptions::new(self.export_format, self.export_rows, mode);
+        if let Some(days) = self.export_range {
+            let today = Local::now().date_naive();
+            options = options.between(today - Days::new(days - 1), today);
+        }
+
+        let path = default_export_path(self.export_format);
+        let file = BufWriter::new(File::create(&path)?);
+        export(
+            &use_apps_store().lock().unwrap().selector().tracked_apps,
+            &options,
+            file,
+        )?;
+        Ok(path.display().to_string())
+    }
+
     fn render_confirm_modal(&mut self, ctx: &Context) {
         let text = format!(
             "Are you sure you want to delete {} app. All data will be erased forever.",
@@ -1,24 +1,37 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::{Local, TimeZone};
 use eframe::{
-    egui::{Label, Layout, RichText, ScrollArea, Separator, Ui},
+    egui::{Label, Layout, ProgressBar, RichText, ScrollArea, Separator, Ui},
     emath::Align,
+    epaint::Color32,
 };
 use tracker_core::{
-    store::apps_store::use_apps_store,
-    tracking::badges::{Badge, BadgeRank},
+    store::{apps_store::use_apps_store, user_store::use_user_store},
+    tracking::{
+        badges::{use_badge_defs, Badge, HabitDef, Progress},
+        unix_secs,
+    },
 };
 
-use super::{
-    configs::{ACCENT, ADDITIONAL, ADDITIONAL_2, ERROR_COLOR, HEADING_COLOR, SUB_HEADING_COLOR},
-    utils::shade_color,
-};
+use super::configs::{HEADING_COLOR, SUB_HEADING_COLOR};
+
+/// For badges whose definition was removed from the badges file
+const UNKNOWN_BADGE_ICON: &str = "🏅";
+/// Progress towards habits goes over the whole history, so it isn't redone every frame
+const PROGRESS_REFRESH: Duration = Duration::from_secs(30);
 
 pub struct BadgesPage {
     list: Vec<AppItem>,
+    progress_at: Option<Instant>,
 }
 
 impl BadgesPage {
     pub fn new() -> Self {
-        Self { list: vec![] }
+        Self {
+            list: vec![],
+            progress_at: None,
+        }
     }
 
     pub fn render(&mut self, ui: &mut Ui) {
@@ -57,9 +70,34 @@ impl BadgesPage {
                 self.list
                     .push(AppItem::new(&item.display_name, &item.badges));
             }
+            self.progress_at = None;
+        }
+        if self
+            .progress_at
+            .is_none_or(|at| at.elapsed() >= PROGRESS_REFRESH)
+        {
+            self.update_progress();
         }
     }
 
+    /// Habits every app is working towards
+    fn update_progress(&mut self) {
+        let mode = use_user_store().selector().tracking_mode;
+        let now = unix_secs(SystemTime::now());
+        let store = use_apps_store();
+        let mut store = store.lock().unwrap();
+
+        for (item, log) in self.list.iter_mut().zip(&store.selector().tracked_apps) {
+            item.habits = use_badge_defs()
+                .habit_progress(log, mode, now)
+                .into_iter()
+                .filter(|(habit, _)| !log.badges.iter().any(|b| b.id == habit.id))
+                .map(|(habit, progress)| HabitItem::new(habit, progress))
+                .collect();
+        }
+        self.progress_at = Some(Instant::now());
+    }
+
     fn render_list(&mut self, ui: &mut Ui) {
         ScrollArea::new([false, true]).show(ui, |ui| {
             for item in &mut self.list {
@@ -83,6 +121,7 @@ struct AppItem {
     name: *const String,
     badges: *const Vec<Badge>,
     badge_list: Vec<BadgeItem>,
+    habits: Vec<HabitItem>,
 }
 
 impl AppItem {
@@ -91,6 +130,7 @@ impl AppItem {
             name,
             badges,
             badge_list: vec![],
+            habits: vec![],
         }
     }
 
@@ -111,9 +151,18 @@ impl AppItem {
     fn render_badges(&mut self, ui: &mut Ui) {
         self.make_list();
 
-        ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
-            for badge_item in &self.badge_list {
-                badge_item.render(ui);
+        ui.with_layout(Layout::top_down(Align::Min), |ui| {
+            ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+                for badge_item in &self.badge_list {
+                    badge_item.render(ui);
+                }
+            });
+            if !self.habits.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    for habit in &self.habits {
+                        habit.render(ui);
+                    }
+                });
             }
         });
     }
@@ -124,40 +173,83 @@ impl AppItem {
         if self.badge_list.len() != badges.len() {
             self.badge_list = vec![];
             for badge in badges.iter() {
-                self.badge_list.push(BadgeItem {
-                    rank: &badge.rank,
-                    description: badge.description.to_owned(),
-                })
+                self.badge_list.push(BadgeItem::new(badge))
             }
         }
     }
 }
 
 struct BadgeItem {
-    rank: *const BadgeRank,
+    icon: String,
+    color: Color32,
     description: String,
 }
 
 impl BadgeItem {
-    fn render(&self, ui: &mut Ui) {
-        let (icon, bg) = unsafe {
-            match *self.rank {
-                BadgeRank::Initial => ("🔓", shade_color(SUB_HEADING_COLOR.to_tuple(), 0.2)),
-                BadgeRank::Common => ("🕑", shade_color((0, 255, 0, 1), -0.3)),
-                BadgeRank::Rare => ("⏳", ADDITIONAL),
-                BadgeRank::Experienced => ("🔥", shade_color(ACCENT.to_tuple(), 0.07)),
-                BadgeRank::Advanced => ("🌀", shade_color(ERROR_COLOR.to_tuple(), 0.0)),
-                BadgeRank::Pro => ("🕞", ADDITIONAL_2),
-                BadgeRank::Insane => ("⏰", shade_color((64, 224, 208, 1), -0.2)),
-                BadgeRank::Lunatic => ("🎴", shade_color(ERROR_COLOR.to_tuple(), -0.4)),
-                BadgeRank::TouchGrass => ("🎉", shade_color(ACCENT.to_tuple(), -0.3)),
-                BadgeRank::Master => ("💎", shade_color(ADDITIONAL_2.to_tuple(), -0.4)),
-            }
-        };
+    fn new(badge: &Badge) -> Self {
+        let look = use_badge_defs().look(&badge.id);
+        Self {
+            icon: look.map_or(UNKNOWN_BADGE_ICON, |(icon, _)| icon).to_owned(),
+            color: look
+                .and_then(|(_, rgb)| rgb)
+                .map_or(SUB_HEADING_COLOR, |(r, g, b)| Color32::from_rgb(r, g, b)),
+            description: match badge
+                .earned_at
+                .and_then(|secs| Local.timestamp_opt(secs as i64, 0).earliest())
+            {
+                Some(time) => format!("{}\nEarned {}", badge.description, time.format("%Y-%m-%d")),
+                None => badge.description.to_owned(),
+            },
+        }
+    }
 
+    fn render(&self, ui: &mut Ui) {
+        let bg = self.color;
         ui.add_space(2.0);
-        ui.button(RichText::new(icon).size(15.0).background_color(bg))
+        ui.button(RichText::new(&self.icon).size(15.0).background_color(bg))
             .on_hover_text(RichText::new(&self.description).size(10.0).color(bg));
         ui.add_space(2.0);
     }
 }
+
+/// Habit not earned yet, with how far the app got
+struct HabitItem {
+    icon: String,
+    color: Color32,
+    description: String,
+    progress: Progress,
+}
+
+impl HabitItem {
+    fn new(habit: &HabitDef, progress: Progress) -> Self {
+        Self {
+            icon: habit.icon.to_owned(),
+            color: habit
+                .rgb()
+                .map_or(SUB_HEADING_COLOR, |(r, g, b)| Color32::from_rgb(r, g, b)),
+            description: habit.description.to_owned(),
+            progress,
+        }
+    }
+
+    fn render(&self, ui: &mut Ui) {
+        ui.label(RichText::new(&self.icon).size(13.0))
+            .on_hover_text(
+                RichText::new(&self.description)
+                    .size(10.0)
+                    .color(self.color),
+            );
+        ui.add(
+            ProgressBar::new(self.progress.fraction())
+                .desired_width(70.0)
+                .fill(self.color)
+                .text(RichText::new(self.progress.to_string()).size(10.0)),
+        )
+        .on_hover_text(
+            RichText::new(&self.description)
+                .size(10.0)
+                .color(self.color),
+        );
+        ui.add_space(4.0);
+    }
+}
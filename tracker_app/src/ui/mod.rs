@@ -4,7 +4,9 @@ mod basics;
 mod configs;
 mod modals;
 mod panels;
+mod profile_page;
 mod router;
+mod toasts;
 mod utils;
 
 use self::{
@@ -12,15 +14,17 @@ use self::{
     badges_page::BadgesPage,
     configs::{configure_fonts, configure_text_styles, configure_visuals, get_win_options},
     modals::confirm_close_modal,
-    panels::{header, side_menu},
+    panels::{error_banner, header, side_menu},
+    profile_page::ProfilePage,
     router::{outlet, Routes},
+    toasts::toasts,
 };
 
 use eframe::{
     egui::{self, CentralPanel},
     run_native, App, CreationContext,
 };
-use tracker_core::{init_data, win_funcs::hide_console_window};
+use tracker_core::{init_client, init_data, ipc::remote, sys::hide_console_window};
 
 /* Bootstrap file (entry point) of the app */
 
@@ -29,6 +33,7 @@ pub struct Main {
     tracked_apps: AppList,
     untracked_apps: NotTrackedAppList,
     badges_page: BadgesPage,
+    profile_page: ProfilePage,
     on_close_dialog_open: bool,
     allow_close: bool,
 }
@@ -44,6 +49,7 @@ impl Main {
             tracked_apps: AppList::new(),
             untracked_apps: NotTrackedAppList::new(),
             badges_page: BadgesPage::new(),
+            profile_page: ProfilePage::new(),
             on_close_dialog_open: false,
             allow_close: false,
         }
@@ -57,8 +63,10 @@ impl Main {
 impl App for Main {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         header(&ctx, frame);
+        error_banner(ctx);
         side_menu(ctx, self);
         CentralPanel::default().show(ctx, |ui| outlet(self, ui));
+        toasts(ctx);
 
         if self.on_close_dialog_open {
             confirm_close_modal(ctx, self, frame);
@@ -67,13 +75,20 @@ impl App for Main {
 
     fn save(&mut self, _storage: &mut dyn eframe::Storage) {}
     fn on_close_event(&mut self) -> bool {
+        // Daemon keeps tracking without the window
+        if remote().is_some() {
+            return true;
+        }
         self.on_close_dialog_open = true;
         self.allow_close
     }
 }
 
 pub fn run_app() {
-    init_data();
+    if let Err(e) = init_client() {
+        eprintln!("Tracking inside the app, daemon is unavailable: {}", e);
+        init_data();
+    }
     hide_console_window();
     run_native(
         "App Tracker",
@@ -87,6 +87,41 @@ pub fn change_proc_name_modal(
         });
 }
 
+/// Daily and weekly limits in minutes. Empty or 0 means no limit
+pub fn limits_modal(
+    ctx: &Context,
+    daily: &mut String,
+    weekly: &mut String,
+    on_confirm: impl FnOnce(&str, &str),
+    on_cancel: impl FnOnce(),
+) {
+    egui::Window::new("Time limits")
+        .resizable(false)
+        .collapsible(false)
+        .frame(get_modal_frame(ctx))
+        .show(ctx, |ui| {
+            ui.with_layout(Layout::top_down(eframe::emath::Align::Center), |ui| {
+                ui.add_space(35.0);
+                ui.label("You're warned at 80% of a limit and once it's reached.");
+
+                input_field(ui, "Minutes per day", daily);
+                input_field(ui, "Minutes per week", weekly);
+
+                ui.add_space(10.0);
+                ui.with_layout(ui.layout().with_main_align(Align::Center), |ui| {
+                    if core_btn(ui, ADDITIONAL_2, "Cancel").clicked() {
+                        on_cancel();
+                    }
+                    if core_btn(ui, ADDITIONAL_2, "Ok").clicked() {
+                        on_confirm(daily, weekly);
+                    }
+                });
+
+                ui.add_space(35.0)
+            })
+        });
+}
+
 fn get_modal_frame(ctx: &Context) -> Frame {
     get_def_frame(ctx)
         .fill(shade_color(MAIN_BG.to_tuple(), 0.03))
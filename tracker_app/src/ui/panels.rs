@@ -3,8 +3,11 @@ use eframe::egui::{self, Context, Id, Label, RichText, Sense, SidePanel, TopBott
 use eframe::emath::{Align, Align2};
 use eframe::epaint::FontId;
 
+use tracker_core::store::apps_store::{use_apps_store, Actions};
+use tracker_core::store::user_store::{use_user_store, UserActions};
+
 use super::basics::text_small_button;
-use super::configs::{get_def_frame, ACCENT, SUB_HEADING_COLOR};
+use super::configs::{get_def_frame, ACCENT, ERROR_COLOR, SUB_HEADING_COLOR};
 use super::router::Routes;
 use super::Main;
 
@@ -16,6 +19,33 @@ pub fn header(ctx: &Context, frame: &mut eframe::Frame) {
     });
 }
 
+/// Latest error of the tracker until the user dismisses it. Errors of tracked apps go first
+pub fn error_banner(ctx: &Context) {
+    let apps_error = use_apps_store().lock().unwrap().selector().error.clone();
+    let is_apps_error = apps_error.is_some();
+    let Some(error) = apps_error.or_else(|| use_user_store().selector().error.clone()) else {
+        return;
+    };
+
+    TopBottomPanel::top("error_banner").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.colored_label(ERROR_COLOR, error);
+            ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
+                text_small_button(ui, "Dismiss", None, || {
+                    if is_apps_error {
+                        use_apps_store()
+                            .lock()
+                            .unwrap()
+                            .dispatch(Actions::CleanErrorMsg)
+                    } else {
+                        use_user_store().dispatch(UserActions::CleanErrorMsg)
+                    }
+                });
+            });
+        });
+    });
+}
+
 struct SideMenuItem {
     title: String,
     route: Routes,
@@ -54,9 +84,10 @@ impl SideMenuItem {
 }
 
 pub fn side_menu(ctx: &Context, app: &mut Main) {
-    let side_menu_data: [SideMenuItem; 3] = [
+    let side_menu_data: [SideMenuItem; 4] = [
         SideMenuItem::new("Home", Routes::Home),
         SideMenuItem::new("Badges", Routes::Badges),
+        SideMenuItem::new("Profile", Routes::Profile),
         SideMenuItem::new("Apps", Routes::NotTrackedApps),
     ];
 
@@ -0,0 +1,124 @@
+use chrono::{Local, TimeZone};
+use eframe::{
+    egui::{Layout, ProgressBar, RichText, ScrollArea, Separator, Ui},
+    emath::Align,
+    epaint::Color32,
+};
+use tracker_core::{store::user_store::use_user_store, tracking::profile::AchievementProgress};
+
+use super::{
+    configs::{HEADING_COLOR, SUB_HEADING_COLOR},
+    utils::format_time,
+};
+
+/// Past months listed under the current one
+const PAST_FAVOURITES: usize = 5;
+
+/// Profile is worked out by the store of the tracked apps whenever they are loaded or saved
+pub struct ProfilePage;
+
+impl ProfilePage {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn render(&mut self, ui: &mut Ui) {
+        let (username, profile) = {
+            let mut store = use_user_store();
+            let user = store.selector();
+            (user.username.to_owned(), user.profile.clone())
+        };
+
+        ui.add_space(5.0);
+        ui.vertical_centered(|ui| ui.heading(format!("Profile of {}", username)));
+        ui.add(Separator::default().spacing(20.0));
+
+        ScrollArea::new([false, true]).show(ui, |ui| {
+            ui.colored_label(
+                HEADING_COLOR,
+                format!(
+                    "{} over {} tracked apps",
+                    format_time(profile.total_secs),
+                    profile.apps_tracked
+                ),
+            );
+            ui.add_space(5.0);
+
+            let this_month = Local::now().format("%Y-%m").to_string();
+            match profile.favourite_of(&this_month) {
+                Some(favourite) => ui.colored_label(
+                    HEADING_COLOR,
+                    format!(
+                        "Most used this month: {} ({})",
+                        favourite.display_name,
+                        format_time(favourite.seconds)
+                    ),
+                ),
+                None => ui.colored_label(SUB_HEADING_COLOR, "Nothing used this month yet"),
+            };
+            for favourite in profile
+                .favourites
+                .iter()
+                .rev()
+                .filter(|f| f.month != this_month)
+                .take(PAST_FAVOURITES)
+            {
+                ui.colored_label(
+                    SUB_HEADING_COLOR,
+                    format!(
+                        "{}: {} ({})",
+                        favourite.month,
+                        favourite.display_name,
+                        format_time(favourite.seconds)
+                    ),
+                );
+            }
+
+            ui.add(Separator::default().spacing(20.0));
+            ui.colored_label(HEADING_COLOR, "Achievements");
+            ui.add_space(5.0);
+            for item in &profile.progress {
+                render_achievement(ui, item);
+                ui.add_space(4.0);
+            }
+        });
+    }
+}
+
+fn render_achievement(ui: &mut Ui, item: &AchievementProgress) {
+    let achievement = &item.achievement;
+    let color = achievement
+        .rgb()
+        .map_or(SUB_HEADING_COLOR, |(r, g, b)| Color32::from_rgb(r, g, b));
+
+    ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
+        ui.label(RichText::new(achievement.icon).size(15.0).color(color));
+        ui.add_space(4.0);
+        match item
+            .earned_at
+            .and_then(|secs| Local.timestamp_opt(secs as i64, 0).earliest())
+        {
+            Some(time) => {
+                ui.colored_label(
+                    color,
+                    format!("{} Earned {}", achievement.id, time.format("%Y-%m-%d")),
+                );
+            }
+            None => {
+                ui.add(
+                    ProgressBar::new(item.progress.fraction())
+                        .desired_width(80.0)
+                        .fill(color)
+                        .text(RichText::new(item.progress.to_string()).size(10.0)),
+                );
+                ui.colored_label(SUB_HEADING_COLOR, achievement.id);
+            }
+        }
+    })
+    .response
+    .on_hover_text(
+        RichText::new(achievement.description)
+            .size(10.0)
+            .color(color),
+    );
+}
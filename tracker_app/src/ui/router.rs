@@ -8,6 +8,7 @@ pub enum Routes {
     // Login,
     Home,
     Badges,
+    Profile,
     NotTrackedApps,
 }
 
@@ -16,6 +17,7 @@ pub fn outlet(app: &mut Main, ui: &mut Ui) {
         // Routes::Login => (),
         Routes::Home => app.tracked_apps.render(ui),
         Routes::Badges => app.badges_page.render(ui),
+        Routes::Profile => app.profile_page.render(ui),
         Routes::NotTrackedApps => app.untracked_apps.render(ui),
     };
 }
@@ -0,0 +1,59 @@
+use std::time::{Duration, SystemTime};
+
+use eframe::egui::{self, Align2, Context};
+use tracker_core::{
+    notify::{use_toasts, NotificationKind},
+    tracking::unix_secs,
+};
+
+use super::{
+    basics::text_small_button,
+    configs::{get_def_frame, ACCENT, ERROR_COLOR, FRAME_ROUNDING, HEADING_COLOR, MAIN_BG},
+    utils::shade_color,
+};
+
+/* Pop-ups in the corner for earned badges and time limits */
+
+/// How long a toast stays on screen
+const TOAST_SECS: u64 = 8;
+
+pub fn toasts(ctx: &Context) {
+    let now = unix_secs(SystemTime::now());
+    let toasts: Vec<_> = use_toasts()
+        .recent()
+        .into_iter()
+        .filter(|t| t.notification.time + TOAST_SECS > now)
+        .collect();
+    if toasts.is_empty() {
+        return;
+    }
+
+    egui::Area::new("toasts")
+        .anchor(Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .show(ctx, |ui| {
+            for toast in toasts {
+                let color = match toast.notification.kind {
+                    NotificationKind::Badge => ACCENT,
+                    NotificationKind::LimitWarning => HEADING_COLOR,
+                    NotificationKind::LimitReached => ERROR_COLOR,
+                };
+                get_def_frame(ctx)
+                    .fill(shade_color(MAIN_BG.to_tuple(), 0.08))
+                    .rounding(FRAME_ROUNDING)
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.vertical(|ui| {
+                                ui.colored_label(color, &toast.notification.title);
+                                ui.label(&toast.notification.body);
+                            });
+                            text_small_button(ui, "x", Some("Dismiss"), || {
+                                use_toasts().dismiss(toast.id)
+                            });
+                        });
+                    });
+                ui.add_space(5.0);
+            }
+        });
+    // Hide expired toasts without waiting for input
+    ctx.request_repaint_after(Duration::from_secs(1));
+}
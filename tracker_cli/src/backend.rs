@@ -0,0 +1,208 @@
+use std::error::Error;
+use std::io;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use tracker_core::config::{update_config, use_config};
+use tracker_core::error::TrackerError;
+use tracker_core::ipc::{Client, Command};
+use tracker_core::storage::{open_default_storage, StorageBackend};
+use tracker_core::sys::get_username;
+use tracker_core::tracking::import::merge_logs;
+use tracker_core::tracking::{unix_secs, TrackLog};
+
+/* Where the CLI reads and changes data. A running daemon owns the data, so changes go through it.
+ * Without one the storage is used directly */
+
+pub enum Backend {
+    Daemon(Client),
+    Local {
+        storage: Arc<dyn StorageBackend>,
+        username: String,
+    },
+}
+
+impl Backend {
+    pub fn open() -> Result<Self, Box<dyn Error>> {
+        match Client::connect() {
+            Ok(client) => Ok(Backend::Daemon(client)),
+            Err(_) => Ok(Backend::Local {
+                storage: open_default_storage(),
+                username: get_username()?,
+            }),
+        }
+    }
+
+    pub fn username(&self) -> Result<String, Box<dyn Error>> {
+        match self {
+            Backend::Daemon(_) => Ok(get_username()?),
+            Backend::Local { username, .. } => Ok(username.to_owned()),
+        }
+    }
+
+    /// Tracked apps of the current user
+    pub fn apps(&self) -> Result<Vec<TrackLog>, Box<dyn Error>> {
+        match self {
+            Backend::Daemon(client) => Ok(client.snapshot()?.tracked_apps),
+            Backend::Local { storage, username } => Ok(storage.load_by_user(username)?),
+        }
+    }
+
+    pub fn app(&self, process_name: &str) -> Result<TrackLog, Box<dyn Error>> {
+        self.apps()?
+            .into_iter()
+            .find(|l| l.process_name == process_name)
+            .ok_or_else(|| TrackerError::NotFound(format!("Tracked app {}", process_name)).into())
+    }
+
+    /// Checks the command makes sense, so the daemon doesn't silently ignore it
+    pub fn apply(&self, command: Command) -> Result<(), Box<dyn Error>> {
+        match &command {
+            Command::Track { process_name, .. } => {
+                if self.app(process_name).is_ok() {
+                    return Err(
+                        io::Error::other(format!("{} is already tracked", process_name)).into(),
+                    );
+                }
+            }
+            Command::Untrack { process_name }
+            | Command::Rename { process_name, .. }
+            | Command::SetRules { process_name, .. }
+            | Command::SetPaused { process_name, .. }
+            | Command::SetLimits { process_name, .. } => {
+                self.app(process_name)?;
+            }
+            Command::SetTrackingMode { .. }
+            | Command::Import { .. }
+            | Command::DismissAlerts
+            | Command::SaveAll
+            | Command::ClearError => (),
+        }
+
+        match self {
+            Backend::Daemon(client) => Ok(client.send(command)?),
+            Backend::Local { storage, .. } => self.apply_local(storage.as_ref(), command),
+        }
+    }
+
+    fn apply_local(
+        &self,
+        storage: &dyn StorageBackend,
+        command: Command,
+    ) -> Result<(), Box<dyn Error>> {
+        let applied = match command {
+            Command::Track {
+                username,
+                process_name,
+            } => {
+                let mut log = TrackLog::new(&username, &process_name, &process_name);
+                // Counting starts once the daemon or the app sees it running
+                log.is_running = false;
+                storage.save(&log)
+            }
+            Command::Untrack { process_name } => storage.delete(&self.app(&process_name)?),
+            Command::Rename {
+                process_name,
+                display_name,
+            } => {
+                let mut log = self.app(&process_name)?;
+                log.set_display_name(&display_name);
+                storage.save(&log)
+            }
+            Command::SetRules {
+                process_name,
+                rules,
+            } => {
+                let mut log = self.app(&process_name)?;
+                log.rules = rules;
+                storage.save(&log)
+            }
+            Command::SetPaused {
+                process_name,
+                paused,
+            } => {
+                let mut log = self.app(&process_name)?;
+                log.paused = paused;
+                storage.save(&log)
+            }
+            Command::SetTrackingMode { mode } => {
+                update_config(|config| config.tracking_mode = mode)
+            }
+            Command::Import { apps } => {
+                let mut logs = self.apps()?;
+                let username = self.username()?;
+                let summary = merge_logs(
+                    &mut logs,
+                    apps,
+                    &username,
+                    use_config().tracking_mode,
+                    unix_secs(SystemTime::now()),
+                );
+                logs.iter()
+                    .filter(|l| summary.changed.contains(&l.process_name))
+                    .try_for_each(|l| storage.save(l))
+            }
+            Command::SetLimits {
+                process_name,
+                limits,
+            } => {
+                let mut log = self.app(&process_name)?;
+                log.limits = limits;
+                storage.save(&log)
+            }
+            // Alerts live in a running tracker only
+            Command::DismissAlerts | Command::SaveAll | Command::ClearError => Ok(()),
+        };
+        applied.map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracker_core::storage::sqlite::SqliteStorage;
+
+    use super::*;
+
+    #[test]
+    fn changes_storage_without_daemon() {
+        let backend = Backend::Local {
+            storage: Arc::new(SqliteStorage::open_in_memory().unwrap()),
+            username: "alice".to_owned(),
+        };
+        let track = || Command::Track {
+            username: "alice".to_owned(),
+            process_name: "game".to_owned(),
+        };
+
+        backend.apply(track()).unwrap();
+        assert!(backend.apply(track()).is_err());
+        backend
+            .apply(Command::Rename {
+                process_name: "game".to_owned(),
+                display_name: "Game".to_owned(),
+            })
+            .unwrap();
+        backend
+            .apply(Command::SetPaused {
+                process_name: "game".to_owned(),
+                paused: true,
+            })
+            .unwrap();
+
+        let game = backend.app("game").unwrap();
+        assert_eq!(game.display_name, "Game");
+        assert!(game.paused);
+
+        backend
+            .apply(Command::Untrack {
+                process_name: "game".to_owned(),
+            })
+            .unwrap();
+        assert!(backend.apps().unwrap().is_empty());
+        assert!(backend
+            .apply(Command::Untrack {
+                process_name: "game".to_owned(),
+            })
+            .is_err());
+    }
+}
@@ -0,0 +1,431 @@
+mod backend;
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+use std::process;
+use std::time::SystemTime;
+
+use chrono::{Days, Local, NaiveDate, TimeZone};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::{json, Value};
+use tracker_core::config::use_config;
+use tracker_core::ipc::Command;
+use tracker_core::storage::schema;
+use tracker_core::tracking::export::{export, ExportFormat, ExportOptions, ExportRows};
+use tracker_core::tracking::import::{find_importer, importer_for_path, merge_logs};
+use tracker_core::tracking::usage::{usage_by_period, Bucket};
+use tracker_core::tracking::{unix_secs, TrackLog, TrackingMode};
+
+use self::backend::Backend;
+
+/* Command line front-end. Talks to the daemon when it's running, otherwise to the storage */
+
+#[derive(Parser)]
+#[command(name = "tracker", version, about = "Query and manage tracked apps")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Tracked apps with their total time
+    List {
+        #[arg(long)]
+        json: bool,
+        /// Time to show. Configured mode if not set
+        #[arg(long, value_enum)]
+        mode: Option<Mode>,
+    },
+    /// Start tracking a process
+    Add {
+        process_name: String,
+        /// Name shown instead of the process name
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Stop tracking an app and erase its history
+    Remove { process_name: String },
+    /// Change the name an app is shown with
+    Rename {
+        process_name: String,
+        display_name: String,
+    },
+    /// Stop counting an app even while it runs
+    Pause { process_name: String },
+    /// Count a paused app again
+    Resume { process_name: String },
+    /// Set how long an app may be used per day or week. Warns at 80% and at the limit
+    Limit {
+        process_name: String,
+        /// Minutes per day. 0 removes the limit
+        #[arg(long)]
+        daily: Option<u64>,
+        /// Minutes per week. 0 removes the limit
+        #[arg(long)]
+        weekly: Option<u64>,
+    },
+    /// Usage per period between two dates
+    Stats {
+        /// First day, YYYY-MM-DD. A week ago by default
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        /// Last day, YYYY-MM-DD. Today by default
+        #[arg(long)]
+        until: Option<NaiveDate>,
+        #[arg(long, value_enum, default_value_t = Period::Day)]
+        by: Period,
+        #[arg(long, value_enum)]
+        mode: Option<Mode>,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Badges earned with every app
+    Badges {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Merge usage history from another machine's stats file or a CSV of app,start,end
+    Import {
+        file: PathBuf,
+        /// Format of the file. Guessed from its extension if not set
+        #[arg(long, value_enum)]
+        format: Option<ImportFormat>,
+    },
+    /// Everything tracked in the stats file format, or usage history as CSV or NDJSON
+    Export {
+        /// File to write. Standard output if not set
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = Format::Stats)]
+        format: Format,
+        /// What a CSV or NDJSON row stands for
+        #[arg(long, value_enum, default_value_t = Rows::Sessions)]
+        rows: Rows,
+        /// First day, YYYY-MM-DD. Whole history by default
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        /// Last day, YYYY-MM-DD
+        #[arg(long)]
+        until: Option<NaiveDate>,
+        #[arg(long, value_enum)]
+        mode: Option<Mode>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    Uptime,
+    Focused,
+}
+
+impl From<Mode> for TrackingMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Uptime => TrackingMode::Uptime,
+            Mode::Focused => TrackingMode::Focused,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Stats,
+    Csv,
+    Ndjson,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ImportFormat {
+    Stats,
+    Csv,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Rows {
+    Sessions,
+    Days,
+}
+
+impl From<Rows> for ExportRows {
+    fn from(rows: Rows) -> Self {
+        match rows {
+            Rows::Sessions => ExportRows::Sessions,
+            Rows::Days => ExportRows::Days,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl From<Period> for Bucket {
+    fn from(period: Period) -> Self {
+        match period {
+            Period::Day => Bucket::Day,
+            Period::Week => Bucket::Week,
+            Period::Month => Bucket::Month,
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli.command) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(command: Commands) -> Result<(), Box<dyn Error>> {
+    let backend = Backend::open()?;
+    let mode = |mode: Option<Mode>| mode.map_or(use_config().tracking_mode, TrackingMode::from);
+
+    match command {
+        Commands::List { json, mode: m } => list(&backend.apps()?, mode(m), json),
+        Commands::Add { process_name, name } => {
+            backend.apply(Command::Track {
+                username: backend.username()?,
+                process_name: process_name.to_owned(),
+            })?;
+            if let Some(name) = name {
+                backend.apply(Command::Rename {
+                    process_name,
+                    display_name: name,
+                })?;
+            }
+            Ok(())
+        }
+        Commands::Remove { process_name } => backend.apply(Command::Untrack { process_name }),
+        Commands::Rename {
+            process_name,
+            display_name,
+        } => backend.apply(Command::Rename {
+            process_name,
+            display_name,
+        }),
+        Commands::Pause { process_name } => backend.apply(Command::SetPaused {
+            process_name,
+            paused: true,
+        }),
+        Commands::Resume { process_name } => backend.apply(Command::SetPaused {
+            process_name,
+            paused: false,
+        }),
+        Commands::Limit {
+            process_name,
+            daily,
+            weekly,
+        } => {
+            let minutes = |m: u64| (m > 0).then_some(m * 60);
+            let mut limits = backend.app(&process_name)?.limits;
+            if let Some(daily) = daily {
+                limits.daily = minutes(daily);
+            }
+            if let Some(weekly) = weekly {
+                limits.weekly = minutes(weekly);
+            }
+            backend.apply(Command::SetLimits {
+                process_name,
+                limits,
+            })
+        }
+        Commands::Stats {
+            since,
+            until,
+            by,
+            mode: m,
+            json,
+        } => {
+            let until = until.unwrap_or_else(|| Local::now().date_naive());
+            let since = since.unwrap_or(until - Days::new(6));
+            if since > until {
+                return Err("--since is after --until".into());
+            }
+            stats(&backend.apps()?, since, until, by.into(), mode(m), json)
+        }
+        Commands::Badges { json } => badges(&backend.apps()?, json),
+        Commands::Import { file, format } => {
+            let importer = match format {
+                Some(ImportFormat::Stats) => find_importer("stats"),
+                Some(ImportFormat::Csv) => find_importer("csv"),
+                None => importer_for_path(&file),
+            }
+            .ok_or("Unknown file format, set it with --format")?;
+            let imported = importer.parse(&fs::read_to_string(&file)?)?;
+
+            // Dry run on a copy tells what the import changes
+            let summary = merge_logs(
+                &mut backend.apps()?,
+                imported.clone(),
+                &backend.username()?,
+                mode(None),
+                unix_secs(SystemTime::now()),
+            );
+            backend.apply(Command::Import { apps: imported })?;
+            println!(
+                "Added {} apps, merged {} apps, {} new sessions",
+                summary.apps_added, summary.apps_merged, summary.sessions_added
+            );
+            Ok(())
+        }
+        Commands::Export {
+            output,
+            format,
+            rows,
+            since,
+            until,
+            mode: m,
+        } => {
+            let apps = backend.apps()?;
+            let format = match format {
+                Format::Stats => {
+                    let data = schema::encode(&apps)?;
+                    match output {
+                        Some(path) => fs::write(path, data)?,
+                        None => println!("{}", data),
+                    }
+                    return Ok(());
+                }
+                Format::Csv => ExportFormat::Csv,
+                Format::Ndjson => ExportFormat::Ndjson,
+            };
+            let mut options = ExportOptions::new(format, rows.into(), mode(m));
+            options.from = since.unwrap_or(options.from);
+            options.to = until.unwrap_or(options.to);
+            if options.from > options.to {
+                return Err("--since is after --until".into());
+            }
+            match output {
+                Some(path) => export(&apps, &options, BufWriter::new(File::create(path)?))?,
+                None => export(&apps, &options, io::stdout().lock())?,
+            };
+            Ok(())
+        }
+    }
+}
+
+fn list(apps: &[TrackLog], mode: TrackingMode, json: bool) -> Result<(), Box<dyn Error>> {
+    if json {
+        let apps: Vec<Value> = apps
+            .iter()
+            .map(|app| {
+                json!({
+                    "process_name": app.process_name,
+                    "display_name": app.display_name,
+                    "running": app.is_running,
+                    "paused": app.paused,
+                    "uptime": app.uptime(),
+                    "focused": app.focused_time(),
+                    "limits": app.limits,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&apps)?);
+        return Ok(());
+    }
+
+    println!(
+        "{:<24} {:<24} {:<8} {:>10}",
+        "NAME", "PROCESS", "STATUS", "TIME"
+    );
+    for app in apps {
+        let status = if app.paused {
+            "paused"
+        } else if app.is_running {
+            "running"
+        } else {
+            "stopped"
+        };
+        println!(
+            "{:<24} {:<24} {:<8} {:>10}",
+            app.display_name,
+            app.process_name,
+            status,
+            format_duration(app.time(mode))
+        );
+    }
+    Ok(())
+}
+
+fn stats(
+    apps: &[TrackLog],
+    since: NaiveDate,
+    until: NaiveDate,
+    bucket: Bucket,
+    mode: TrackingMode,
+    json: bool,
+) -> Result<(), Box<dyn Error>> {
+    let usage = usage_by_period(apps, bucket, since, until, mode);
+    if json {
+        let usage: Vec<Value> = usage
+            .iter()
+            .map(|app| {
+                json!({
+                    "process_name": app.process_name,
+                    "display_name": app.display_name,
+                    "total": app.total(),
+                    "buckets": app.buckets.iter().map(|b| json!({
+                        "start": b.start.to_string(),
+                        "end": b.end.to_string(),
+                        "seconds": b.seconds,
+                    })).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&usage)?);
+        return Ok(());
+    }
+
+    for app in &usage {
+        println!(
+            "{} ({})  {}",
+            app.display_name,
+            app.process_name,
+            format_duration(app.total())
+        );
+        for b in &app.buckets {
+            println!("  {}  {:>10}", b.start, format_duration(b.seconds));
+        }
+    }
+    Ok(())
+}
+
+fn badges(apps: &[TrackLog], json: bool) -> Result<(), Box<dyn Error>> {
+    if json {
+        let badges: Vec<Value> = apps
+            .iter()
+            .map(|app| {
+                json!({
+                    "process_name": app.process_name,
+                    "display_name": app.display_name,
+                    "badges": app.badges,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&badges)?);
+        return Ok(());
+    }
+
+    for app in apps {
+        println!("{}", app.display_name);
+        for badge in &app.badges {
+            let earned = badge
+                .earned_at
+                .and_then(|secs| Local.timestamp_opt(secs as i64, 0).earliest())
+                .map_or_else(|| "-".to_owned(), |t| t.format("%Y-%m-%d").to_string());
+            println!("  {:<12} {:<10} {}", badge.id, earned, badge.description);
+        }
+    }
+    Ok(())
+}
+
+fn format_duration(secs: u64) -> String {
+    format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+}
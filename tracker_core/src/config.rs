@@ -0,0 +1,152 @@
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::TrackerError;
+use crate::storage::json::write_atomic;
+use crate::tracking::TrackingMode;
+
+/* User settings read from `config.toml` in the platform config directory */
+
+/// Env variable overriding the directory where tracking data is kept
+pub const DATA_DIR_ENV_VAR: &str = "TRACKER_DATA_DIR";
+/// Env variable overriding the location of the config file
+pub const CONFIG_PATH_ENV_VAR: &str = "TRACKER_CONFIG";
+
+const APP_DIR: &str = "tracker";
+const CONFIG_FILE: &str = "config.toml";
+
+lazy_static! {
+    /// Config and why the defaults are used instead, if the file couldn't be read
+    static ref CONFIG: (Config, Option<String>) = Config::load_default();
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Where stats are stored. Platform data directory if not set
+    pub data_dir: Option<PathBuf>,
+    /// Which time badges and reports use
+    pub tracking_mode: TrackingMode,
+    /// Seconds without keyboard or mouse input after which counting stops. 0 disables it
+    pub idle_timeout_secs: u64,
+    /// Also record a session for every process of an app, with its pid and start time
+    pub instance_sessions: bool,
+    /// Show earned badges and time limit alerts as desktop notifications, not only in the app
+    pub desktop_notifications: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            data_dir: None,
+            tracking_mode: TrackingMode::default(),
+            idle_timeout_secs: 5 * 60,
+            instance_sessions: false,
+            desktop_notifications: true,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config. Missing file gives the defaults
+    pub fn load(path: &Path) -> Result<Self, TrackerError> {
+        match fs::read_to_string(path) {
+            Ok(data) => Ok(toml::from_str(&data)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TrackerError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        write_atomic(path, toml::to_string_pretty(self)?.as_bytes())
+    }
+
+    fn load_default() -> (Self, Option<String>) {
+        let Some(path) = config_path() else {
+            return (Self::default(), None);
+        };
+        match Self::load(&path) {
+            Ok(config) => (config, None),
+            Err(e) => {
+                let msg = format!("Couldn't read config {}: {}", path.display(), e);
+                eprintln!("{}", msg);
+                (Self::default(), Some(msg))
+            }
+        }
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        (self.idle_timeout_secs > 0).then(|| Duration::from_secs(self.idle_timeout_secs))
+    }
+
+    /// Directory for tracking data. Env variable wins over the config file,
+    /// then goes the platform data directory (XDG_DATA_HOME on Linux, AppData on Windows)
+    pub fn data_dir(&self) -> PathBuf {
+        env::var_os(DATA_DIR_ENV_VAR)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| self.data_dir.clone())
+            .or_else(|| dirs::data_dir().map(|dir| dir.join(APP_DIR)))
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+}
+
+/// Config as it was at startup
+pub fn use_config() -> &'static Config {
+    &CONFIG.0
+}
+
+/// Why the config file was ignored, if it was
+pub fn config_error() -> Option<&'static str> {
+    CONFIG.1.as_deref()
+}
+
+/// Changes the config file on disk. Takes effect for settings read after the call
+pub fn update_config(f: impl FnOnce(&mut Config)) -> Result<(), TrackerError> {
+    let path =
+        config_path().ok_or_else(|| TrackerError::NotFound("Config directory".to_owned()))?;
+    let mut config = Config::load(&path)?;
+    f(&mut config);
+    config.save(&path)
+}
+
+/// `config.toml` in the platform config directory unless overridden by [`CONFIG_PATH_ENV_VAR`]
+pub fn config_path() -> Option<PathBuf> {
+    env::var_os(CONFIG_PATH_ENV_VAR)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::config_dir().map(|dir| dir.join(APP_DIR).join(CONFIG_FILE)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_data_dir_from_file() {
+        let path = env::temp_dir().join(format!("tracker_config_{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "data_dir = \"/srv/tracker\"\ntracking_mode = \"focused\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.data_dir, Some(PathBuf::from("/srv/tracker")));
+        assert_eq!(config.tracking_mode, TrackingMode::Focused);
+        if env::var_os(DATA_DIR_ENV_VAR).is_none() {
+            assert_eq!(config.data_dir(), PathBuf::from("/srv/tracker"));
+        }
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Config::load(&path).unwrap(), Config::default());
+    }
+}
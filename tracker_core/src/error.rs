@@ -0,0 +1,114 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io;
+
+/* Errors returned by the public APIs of the crate. Stores keep them as text for the UI to show */
+
+#[derive(Debug)]
+pub enum TrackerError {
+    /// Reading or writing files failed
+    Io(io::Error),
+    /// Data, definitions or patterns that can't be understood
+    Parse(String),
+    /// Running processes couldn't be listed or read
+    ProcessAccess(String),
+    /// Tracked app or other item that doesn't exist
+    NotFound(String),
+    /// Storage backend failed
+    Storage(String),
+    /// Talking to the tracking daemon failed
+    Ipc(String),
+    /// Window system or notification service of the desktop failed
+    Desktop(String),
+}
+
+impl Display for TrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse(msg) => write!(f, "{}", msg),
+            Self::ProcessAccess(msg) => write!(f, "Couldn't access processes: {}", msg),
+            Self::NotFound(what) => write!(f, "{} not found", what),
+            Self::Storage(msg) => write!(f, "Storage failed: {}", msg),
+            Self::Ipc(msg) => write!(f, "Tracking daemon failed: {}", msg),
+            Self::Desktop(msg) => write!(f, "Desktop is unavailable: {}", msg),
+        }
+    }
+}
+
+impl Error for TrackerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TrackerError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for TrackerError {
+    fn from(e: serde_json::Error) -> Self {
+        // Errors of the underlying writer aren't about the data
+        match e.io_error_kind() {
+            Some(kind) => Self::Io(io::Error::new(kind, e)),
+            None => Self::Parse(format!("Invalid JSON: {}", e)),
+        }
+    }
+}
+
+impl From<toml::de::Error> for TrackerError {
+    fn from(e: toml::de::Error) -> Self {
+        Self::Parse(format!("Invalid TOML: {}", e.message()))
+    }
+}
+
+impl From<toml::ser::Error> for TrackerError {
+    fn from(e: toml::ser::Error) -> Self {
+        Self::Parse(format!("Couldn't write TOML: {}", e))
+    }
+}
+
+impl From<rusqlite::Error> for TrackerError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Storage(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    struct FullDisk;
+
+    impl Write for FullDisk {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn converts_sources_by_kind() {
+        let parse = serde_json::from_str::<u32>("nope").unwrap_err();
+        assert!(matches!(TrackerError::from(parse), TrackerError::Parse(_)));
+
+        // Writer failing isn't the data's fault
+        let write = serde_json::to_writer(FullDisk, &1).unwrap_err();
+        assert!(matches!(TrackerError::from(write), TrackerError::Io(_)));
+
+        assert_eq!(
+            TrackerError::NotFound("Tracked app game".to_owned()).to_string(),
+            "Tracked app game not found"
+        );
+    }
+}
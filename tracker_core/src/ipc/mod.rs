@@ -0,0 +1,487 @@
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+#[cfg(unix)]
+use crate::config::use_config;
+use crate::error::TrackerError;
+use crate::notify::{use_toasts, Toast};
+use crate::store::apps_store::{Actions, AppsStore};
+use crate::store::user_store::{use_user_store, UserActions};
+use crate::sys::ProcessInfo;
+use crate::tracking::limits::{LimitAlert, UsageLimits};
+use crate::tracking::profile::UserProfile;
+use crate::tracking::rules::{LivePids, TrackRule};
+use crate::tracking::{TrackLog, TrackingMode};
+
+#[cfg(unix)]
+use self::unix::{connect, Listener, Stream};
+#[cfg(windows)]
+use self::windows::{connect, Listener, Stream};
+
+/* Local endpoint of the tracking daemon. Every message is a single line of JSON,
+ * each request gets exactly one response */
+
+/// Env variable overriding the socket path (pipe name on Windows)
+pub const ENDPOINT_ENV_VAR: &str = "TRACKER_SOCKET";
+
+#[cfg(unix)]
+const SOCKET_FILE: &str = "tracker.sock";
+/// Pipe names are machine-wide, so each user gets their own
+#[cfg(windows)]
+const PIPE_NAME: &str = r"\\.\pipe\tracker";
+
+static REMOTE: OnceLock<Client> = OnceLock::new();
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Ping,
+    /// Whole state, or [`Response::Unchanged`] if it's still at revision `since`
+    Snapshot {
+        #[serde(default)]
+        since: Option<u64>,
+    },
+    Command {
+        command: Command,
+    },
+    /// Saves everything and stops the daemon
+    Shutdown,
+}
+
+/// Changes a client can make. Everything else is up to the daemon
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "name", rename_all = "snake_case")]
+pub enum Command {
+    Track {
+        username: String,
+        process_name: String,
+    },
+    Untrack {
+        process_name: String,
+    },
+    Rename {
+        process_name: String,
+        display_name: String,
+    },
+    SetRules {
+        process_name: String,
+        rules: Vec<TrackRule>,
+    },
+    SetPaused {
+        process_name: String,
+        paused: bool,
+    },
+    SetTrackingMode {
+        mode: TrackingMode,
+    },
+    Import {
+        apps: Vec<TrackLog>,
+    },
+    SetLimits {
+        process_name: String,
+        limits: UsageLimits,
+    },
+    DismissAlerts,
+    SaveAll,
+    ClearError,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Ok,
+    Pong,
+    Snapshot { snapshot: Box<Snapshot> },
+    Unchanged,
+    Error { message: String },
+}
+
+/// What the GUI shows, as the daemon sees it
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Snapshot {
+    /// Grows with every change of the daemon, across restarts too
+    #[serde(default)]
+    pub revision: u64,
+    pub tracked_apps: Vec<TrackLog>,
+    pub untracked_apps: Vec<ProcessInfo>,
+    pub running_apps: LivePids,
+    pub focused_app: Option<String>,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub limit_alerts: Vec<LimitAlert>,
+    /// Notifications for the app window, newest first
+    #[serde(default)]
+    pub toasts: Vec<Toast>,
+    #[serde(default)]
+    pub profile: UserProfile,
+}
+
+/// Socket in the runtime directory (data directory if there is none), or a named pipe of the user on Windows
+pub fn endpoint() -> PathBuf {
+    if let Some(path) = env::var_os(ENDPOINT_ENV_VAR).filter(|path| !path.is_empty()) {
+        return PathBuf::from(path);
+    }
+    #[cfg(unix)]
+    return dirs::runtime_dir()
+        .map(|dir| dir.join("tracker"))
+        .unwrap_or_else(|| use_config().data_dir())
+        .join(SOCKET_FILE);
+    #[cfg(windows)]
+    return PathBuf::from(format!(
+        "{}-{}",
+        PIPE_NAME,
+        self::windows::user_sid()
+            .or_else(|_| crate::sys::get_username())
+            .unwrap_or_default()
+    ));
+}
+
+/// Daemon this process is a client of, if any
+pub fn remote() -> Option<&'static Client> {
+    REMOTE.get()
+}
+
+/// Makes the stores of this process a view of the daemon. Has no effect once the app store is in use
+pub fn use_remote(client: Client) -> bool {
+    REMOTE.set(client).is_ok()
+}
+
+struct Connection {
+    reader: BufReader<Stream>,
+    writer: Stream,
+}
+
+impl Connection {
+    fn new(stream: Stream) -> io::Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    fn send<T: serde::Serialize>(&mut self, msg: &T) -> Result<(), TrackerError> {
+        let mut line = serde_json::to_vec(msg)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// `None` once the other side has hung up
+    fn recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>, TrackerError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&line)?))
+    }
+}
+
+/// Connection to the daemon. Reconnects on the next request after an error
+pub struct Client {
+    endpoint: PathBuf,
+    conn: Mutex<Option<Connection>>,
+}
+
+impl Client {
+    pub fn connect() -> Result<Self, TrackerError> {
+        Self::connect_to(&endpoint())
+    }
+
+    pub fn connect_to(endpoint: &Path) -> Result<Self, TrackerError> {
+        let client = Self {
+            endpoint: endpoint.to_owned(),
+            conn: Mutex::new(Some(Connection::new(connect(endpoint)?)?)),
+        };
+        match client.request(&Request::Ping)? {
+            Response::Pong => Ok(client),
+            other => Err(TrackerError::Ipc(format!(
+                "Unexpected answer to ping: {:?}",
+                other
+            ))),
+        }
+    }
+
+    pub fn request(&self, request: &Request) -> Result<Response, TrackerError> {
+        let mut conn = self.conn.lock().unwrap();
+        if conn.is_none() {
+            *conn = Some(Connection::new(connect(&self.endpoint)?)?);
+        }
+        let result = conn
+            .as_mut()
+            .ok_or_else(|| TrackerError::Ipc("Not connected".to_owned()))
+            .and_then(|conn| {
+                conn.send(request)?;
+                conn.recv::<Response>()?
+                    .ok_or_else(|| TrackerError::Ipc("Daemon closed the connection".to_owned()))
+            });
+        if result.is_err() {
+            *conn = None;
+        }
+        match result? {
+            Response::Error { message } => Err(TrackerError::Ipc(message)),
+            response => Ok(response),
+        }
+    }
+
+    pub fn snapshot(&self) -> Result<Snapshot, TrackerError> {
+        self.snapshot_since(None)?
+            .ok_or_else(|| TrackerError::Ipc("Expected a snapshot".to_owned()))
+    }
+
+    /// State of the daemon if it has changed since the given revision. Cheap to ask when it hasn't
+    pub fn snapshot_since(&self, since: Option<u64>) -> Result<Option<Snapshot>, TrackerError> {
+        match self.request(&Request::Snapshot { since })? {
+            Response::Snapshot { snapshot } => Ok(Some(*snapshot)),
+            Response::Unchanged => Ok(None),
+            other => Err(TrackerError::Ipc(format!(
+                "Expected a snapshot, got {:?}",
+                other
+            ))),
+        }
+    }
+
+    pub fn send(&self, command: Command) -> Result<(), TrackerError> {
+        self.request(&Request::Command { command })?;
+        Ok(())
+    }
+}
+
+/// Answers clients on the endpoint from its own threads.
+/// Returned channel receives a message when a client asks the daemon to shut down
+pub fn serve(store: AppsStore, endpoint: &Path) -> Result<Receiver<()>, TrackerError> {
+    let listener = Listener::bind(endpoint)?;
+    let (shutdown_tx, shutdown_rx) = mpsc::channel();
+    // Revisions of a restarted daemon start above the ones of the previous run,
+    // which makes far fewer changes than milliseconds
+    let epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+
+    thread::spawn(move || loop {
+        let stream = match listener.accept() {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Couldn't accept a client: {}", e);
+                continue;
+            }
+        };
+        let store = store.clone();
+        let shutdown_tx = shutdown_tx.clone();
+        thread::spawn(move || {
+            let mut conn = match Connection::new(stream) {
+                Ok(conn) => conn,
+                Err(e) => return eprintln!("Couldn't set up a client: {}", e),
+            };
+            loop {
+                let response = match conn.recv::<Request>() {
+                    Ok(Some(Request::Shutdown)) => {
+                        store.lock().unwrap().dispatch(Actions::SaveAllData);
+                        let _ = conn.send(&Response::Ok);
+                        let _ = shutdown_tx.send(());
+                        return;
+                    }
+                    Ok(Some(request)) => handle(&store, request, epoch),
+                    Ok(None) => return,
+                    Err(e) => Response::Error {
+                        message: format!("Bad request: {}", e),
+                    },
+                };
+                if let Err(e) = conn.send(&response) {
+                    return eprintln!("Client went away: {}", e);
+                }
+            }
+        });
+    });
+    Ok(shutdown_rx)
+}
+
+fn handle(store: &AppsStore, request: Request, epoch: u64) -> Response {
+    match request {
+        Request::Ping => Response::Pong,
+        Request::Snapshot { since } => {
+            let mut store = store.lock().unwrap();
+            let state = store.selector();
+            // Toasts are made outside the store
+            let revision = epoch + state.revision + use_toasts().last_id();
+            if since == Some(revision) {
+                return Response::Unchanged;
+            }
+            Response::Snapshot {
+                snapshot: Box::new(Snapshot {
+                    revision,
+                    tracked_apps: state.tracked_apps.clone(),
+                    untracked_apps: state.untracked_apps.clone(),
+                    running_apps: state.running_apps.clone(),
+                    focused_app: state.focused_app.clone(),
+                    limit_alerts: state.limit_alerts.clone(),
+                    toasts: use_toasts().recent(),
+                    profile: use_user_store().selector().profile.clone(),
+                    error: state.error.clone(),
+                }),
+            }
+        }
+        Request::Command { command } => {
+            match command {
+                Command::SetTrackingMode { mode } => {
+                    use_user_store().dispatch(UserActions::SetTrackingMode(mode))
+                }
+                command => store.lock().unwrap().dispatch(command.into_action()),
+            }
+            Response::Ok
+        }
+        Request::Shutdown => Response::Ok,
+    }
+}
+
+impl Command {
+    /// Store action doing the same in the daemon. Tracking mode lives in the user store
+    pub fn into_action(self) -> Actions {
+        match self {
+            Command::Track {
+                username,
+                process_name,
+            } => Actions::AddTrackedApp(username, process_name),
+            Command::Untrack { process_name } => Actions::DeleteTrackedApp(process_name),
+            Command::Rename {
+                process_name,
+                display_name,
+            } => Actions::ChangeTrackedAppName(process_name, display_name),
+            Command::SetRules {
+                process_name,
+                rules,
+            } => Actions::SetTrackingRules(process_name, rules),
+            Command::SetPaused {
+                process_name,
+                paused,
+            } => Actions::SetPaused(process_name, paused),
+            Command::Import { apps } => Actions::ImportApps(apps),
+            Command::SetLimits {
+                process_name,
+                limits,
+            } => Actions::SetLimits(process_name, limits),
+            Command::DismissAlerts => Actions::DismissLimitAlerts,
+            Command::SaveAll => Actions::SaveAllData,
+            Command::ClearError => Actions::CleanErrorMsg,
+            Command::SetTrackingMode { .. } => Actions::None,
+        }
+    }
+
+    /// Command for a store action a client wants to make. `None` for actions only the daemon does
+    pub fn from_action(action: &Actions) -> Option<Self> {
+        Some(match action.clone() {
+            Actions::AddTrackedApp(username, process_name) => Command::Track {
+                username,
+                process_name,
+            },
+            Actions::DeleteTrackedApp(process_name) => Command::Untrack { process_name },
+            Actions::ChangeTrackedAppName(process_name, display_name) => Command::Rename {
+                process_name,
+                display_name,
+            },
+            Actions::SetTrackingRules(process_name, rules) => Command::SetRules {
+                process_name,
+                rules,
+            },
+            Actions::SetPaused(process_name, paused) => Command::SetPaused {
+                process_name,
+                paused,
+            },
+            Actions::ImportApps(apps) => Command::Import { apps },
+            Actions::SetLimits(process_name, limits) => Command::SetLimits {
+                process_name,
+                limits,
+            },
+            Actions::DismissLimitAlerts => Command::DismissAlerts,
+            Actions::SaveAllData | Actions::SaveData(_) => Command::SaveAll,
+            Actions::CleanErrorMsg => Command::ClearError,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    use crate::notify::Notifier;
+    use crate::storage::sqlite::SqliteStorage;
+    use crate::store::apps_store::create_apps_store;
+    use crate::sys::clock::ManualClock;
+    use crate::sys::scripted::ScriptedProcessSource;
+    use crate::sys::watcher::ClockNotifier;
+    use crate::tracking::TrackingEnv;
+
+    use super::*;
+
+    #[test]
+    fn client_changes_daemon_store() {
+        let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+        let source = Arc::new(ScriptedProcessSource::new(clock.clone()));
+        let store = create_apps_store(TrackingEnv {
+            source: source.clone(),
+            notifier: Arc::new(ClockNotifier(clock.clone())),
+            focus: source.clone(),
+            idle: source,
+            idle_timeout: None,
+            instance_sessions: false,
+            notifications: Notifier::default(),
+            clock,
+            storage: Arc::new(SqliteStorage::open_in_memory().unwrap()),
+        });
+
+        let path = env::temp_dir().join(format!("tracker_ipc_{}.sock", std::process::id()));
+        let shutdown = serve(store.clone(), &path).unwrap();
+        // Second daemon on the same endpoint is refused
+        assert!(serve(store.clone(), &path).is_err());
+
+        let client = Client::connect_to(&path).unwrap();
+        client
+            .send(Command::Track {
+                username: "alice".to_owned(),
+                process_name: "game".to_owned(),
+            })
+            .unwrap();
+        client
+            .send(Command::Rename {
+                process_name: "game".to_owned(),
+                display_name: "Game".to_owned(),
+            })
+            .unwrap();
+
+        let snapshot = client.snapshot().unwrap();
+        assert_eq!(snapshot.tracked_apps.len(), 1);
+        assert_eq!(snapshot.tracked_apps[0].display_name, "Game");
+
+        // Nothing is sent again until something changes
+        let revision = Some(snapshot.revision);
+        assert!(client.snapshot_since(revision).unwrap().is_none());
+        client
+            .send(Command::SetPaused {
+                process_name: "game".to_owned(),
+                paused: true,
+            })
+            .unwrap();
+        let changed = client.snapshot_since(revision).unwrap().unwrap();
+        assert!(changed.revision > snapshot.revision);
+
+        client.request(&Request::Shutdown).unwrap();
+        shutdown.recv().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
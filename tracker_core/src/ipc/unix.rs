@@ -0,0 +1,35 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+pub type Stream = UnixStream;
+
+/// Unix domain socket. A socket file left by a crashed daemon is replaced
+pub struct Listener(UnixListener);
+
+impl Listener {
+    pub fn bind(path: &Path) -> Result<Self> {
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(Error::new(
+                    ErrorKind::AddrInUse,
+                    format!("Another daemon is listening on {}", path.display()),
+                ));
+            }
+            fs::remove_file(path)?;
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(Self(UnixListener::bind(path)?))
+    }
+
+    pub fn accept(&self) -> Result<Stream> {
+        Ok(self.0.accept()?.0)
+    }
+}
+
+pub fn connect(path: &Path) -> Result<Stream> {
+    UnixStream::connect(path)
+}
@@ -0,0 +1,158 @@
+use std::ffi::OsStr;
+use std::fs::{File, OpenOptions};
+use std::io::{Error, Result};
+use std::iter;
+use std::mem;
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::io::FromRawHandle;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use winapi::shared::minwindef::{DWORD, FALSE};
+use winapi::shared::sddl::{
+    ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+};
+use winapi::shared::winerror::ERROR_PIPE_CONNECTED;
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::minwinbase::SECURITY_ATTRIBUTES;
+use winapi::um::namedpipeapi::ConnectNamedPipe;
+use winapi::um::processthreadsapi::{GetCurrentProcess, OpenProcessToken};
+use winapi::um::securitybaseapi::GetTokenInformation;
+use winapi::um::winbase::{
+    CreateNamedPipeW, LocalFree, FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX,
+    PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES,
+    PIPE_WAIT,
+};
+use winapi::um::winnt::{TokenUser, HANDLE, LPWSTR, PSECURITY_DESCRIPTOR, TOKEN_QUERY, TOKEN_USER};
+
+pub type Stream = File;
+
+const BUFFER_SIZE: u32 = 64 * 1024;
+
+/// Named pipe. Every client gets its own instance of the pipe
+pub struct Listener {
+    name: Vec<u16>,
+    /// SDDL of the pipe, which only the user running the daemon can open
+    security: Vec<u16>,
+    /// Instance created while binding, so a second daemon fails right away
+    first: std::sync::Mutex<Option<File>>,
+}
+
+impl Listener {
+    pub fn bind(path: &Path) -> Result<Self> {
+        let name = wide(path.as_os_str());
+        let security = wide(OsStr::new(&format!("D:P(A;;GA;;;{})", user_sid()?)));
+        let first = create_instance(&name, &security, FILE_FLAG_FIRST_PIPE_INSTANCE)?;
+        Ok(Self {
+            name,
+            security,
+            first: std::sync::Mutex::new(Some(first)),
+        })
+    }
+
+    pub fn accept(&self) -> Result<Stream> {
+        let pipe = match self.first.lock().unwrap().take() {
+            Some(pipe) => pipe,
+            None => create_instance(&self.name, &self.security, 0)?,
+        };
+        if unsafe { ConnectNamedPipe(pipe_handle(&pipe), ptr::null_mut()) } == 0 {
+            let e = Error::last_os_error();
+            // Client connected between creating the instance and waiting for it
+            if e.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) {
+                return Err(e);
+            }
+        }
+        Ok(pipe)
+    }
+}
+
+fn create_instance(name: &[u16], security: &[u16], flags: u32) -> Result<File> {
+    let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+    if unsafe {
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            security.as_ptr(),
+            SDDL_REVISION_1 as DWORD,
+            &mut descriptor,
+            ptr::null_mut(),
+        )
+    } == 0
+    {
+        return Err(Error::last_os_error());
+    }
+    let mut attributes = SECURITY_ATTRIBUTES {
+        nLength: mem::size_of::<SECURITY_ATTRIBUTES>() as DWORD,
+        lpSecurityDescriptor: descriptor,
+        bInheritHandle: FALSE,
+    };
+    let handle = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            PIPE_ACCESS_DUPLEX | flags,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            &mut attributes,
+        )
+    };
+    let e = Error::last_os_error();
+    unsafe { LocalFree(descriptor) };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(e);
+    }
+    Ok(unsafe { File::from_raw_handle(handle.cast()) })
+}
+
+fn pipe_handle(pipe: &File) -> winapi::um::winnt::HANDLE {
+    use std::os::windows::io::AsRawHandle;
+    pipe.as_raw_handle().cast()
+}
+
+pub fn connect(path: &Path) -> Result<Stream> {
+    OpenOptions::new().read(true).write(true).open(path)
+}
+
+/// SID of the user running this process, like `S-1-5-21-...`
+pub fn user_sid() -> Result<String> {
+    let mut token: HANDLE = ptr::null_mut();
+    if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+        return Err(Error::last_os_error());
+    }
+    let mut size: DWORD = 0;
+    unsafe { GetTokenInformation(token, TokenUser, ptr::null_mut(), 0, &mut size) };
+    // u64 keeps the pointers inside TOKEN_USER aligned
+    let mut buffer = vec![0u64; size as usize / mem::size_of::<u64>() + 1];
+    let is_read = unsafe {
+        GetTokenInformation(
+            token,
+            TokenUser,
+            buffer.as_mut_ptr().cast(),
+            size,
+            &mut size,
+        )
+    } != 0;
+    let e = Error::last_os_error();
+    unsafe { CloseHandle(token) };
+    if !is_read {
+        return Err(e);
+    }
+
+    let user = unsafe { &*(buffer.as_ptr() as *const TOKEN_USER) };
+    let mut sid: LPWSTR = ptr::null_mut();
+    if unsafe { ConvertSidToStringSidW(user.User.Sid, &mut sid) } == 0 {
+        return Err(Error::last_os_error());
+    }
+    let sid_string = unsafe {
+        let len = (0..).take_while(|&i| *sid.add(i) != 0).count();
+        let sid_string = String::from_utf16_lossy(slice::from_raw_parts(sid, len));
+        LocalFree(sid.cast());
+        sid_string
+    };
+    Ok(sid_string)
+}
+
+fn wide(s: &OsStr) -> Vec<u16> {
+    s.encode_wide().chain(iter::once(0)).collect()
+}
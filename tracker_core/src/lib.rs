@@ -1,32 +1,52 @@
-use std::{thread, time::Duration};
+use std::process::{Command, Stdio};
+use std::{env, sync::Arc, thread, time::Duration};
+
+use config::config_error;
+use error::TrackerError;
+use ipc::{remote, use_remote, Client};
 use store::{apps_store::Actions, user_store::UserActions};
-use tracking::start_supervisor_thread;
+use tracking::badges::badge_defs_error;
+use tracking::{start_supervisor_thread, use_tracking_env, Tracker};
 
 use crate::store::{apps_store::use_apps_store, user_store::use_user_store};
 
+pub mod config;
+pub mod error;
+pub mod ipc;
+pub mod notify;
+pub mod storage;
 pub mod store;
+pub mod sys;
 pub mod tracking;
-pub mod win_funcs;
 
 #[macro_use]
 extern crate lazy_static;
 
 pub fn init_data() {
+    let env = use_tracking_env();
+    let clock = env.clock.clone();
+
     thread::spawn(move || {
         // Init user related info
         use_user_store().dispatch(UserActions::InitConfig);
+        // Defaults are used instead of files that couldn't be read
+        for msg in [config_error(), badge_defs_error()].into_iter().flatten() {
+            use_apps_store()
+                .lock()
+                .unwrap()
+                .dispatch(Actions::ReportError(msg.to_owned()));
+        }
         let mut tries: u8 = 0;
         // fetch prev tracking data
         loop {
             if tries > 5
                 || use_apps_store().lock().unwrap().selector().is_error_tracked
-                || use_apps_store()
+                || !use_apps_store()
                     .lock()
                     .unwrap()
                     .selector()
                     .tracked_apps
-                    .len()
-                    > 0
+                    .is_empty()
             {
                 break;
             }
@@ -35,9 +55,72 @@ pub fn init_data() {
                 .unwrap()
                 .dispatch(Actions::FetchTrackedApps);
             tries += 1;
+            clock.sleep(Duration::from_secs(1));
+        }
+    });
+
+    start_supervisor_thread(Tracker::new(env, Arc::downgrade(&use_apps_store())));
+}
+
+/// Daemon binary, expected next to the binary of the client
+pub const DAEMON_BIN: &str = "tracker_daemon";
+
+/// Connects to the tracking daemon, starting it first if it's not running.
+/// Stores of this process then mirror the daemon, so [`init_data`] must not be called.
+/// Has to run before anything uses the stores
+pub fn init_client() -> Result<(), TrackerError> {
+    let client = match Client::connect() {
+        Ok(client) => client,
+        Err(_) => {
+            spawn_daemon()?;
+            connect_to_started_daemon()?
+        }
+    };
+    use_remote(client);
+    use_user_store().dispatch(UserActions::InitConfig);
+
+    thread::spawn(|| {
+        let Some(client) = remote() else {
+            return;
+        };
+        loop {
+            let revision = use_apps_store().lock().unwrap().selector().revision;
+            // Asks without holding the store, so the window doesn't wait for the daemon
+            let action = match client.snapshot_since(Some(revision)) {
+                Ok(Some(snapshot)) => Actions::ApplySnapshot(Box::new(snapshot)),
+                Ok(None) => Actions::None,
+                Err(e) => Actions::ReportError(format!("Tracking daemon is unreachable: {}", e)),
+            };
+            use_apps_store().lock().unwrap().dispatch(action);
             thread::sleep(Duration::from_secs(1));
         }
     });
+    Ok(())
+}
+
+fn spawn_daemon() -> Result<(), TrackerError> {
+    let path =
+        env::current_exe()?.with_file_name(format!("{}{}", DAEMON_BIN, env::consts::EXE_SUFFIX));
+    Command::new(&path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| TrackerError::Ipc(format!("Couldn't start {}: {}", path.display(), e)))?;
+    Ok(())
+}
 
-    start_supervisor_thread();
+/// Daemon needs a moment to load data and open the endpoint
+fn connect_to_started_daemon() -> Result<Client, TrackerError> {
+    let mut tries: u8 = 0;
+    loop {
+        match Client::connect() {
+            Ok(client) => return Ok(client),
+            Err(e) if tries >= 20 => return Err(e),
+            Err(_) => {
+                tries += 1;
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+    }
 }
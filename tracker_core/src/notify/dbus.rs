@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+use zbus::blocking::{connection, Connection};
+use zbus::zvariant::Value;
+
+use crate::error::TrackerError;
+
+use super::{Notification, NotificationSink};
+
+/* Desktop notifications through the freedesktop notification service on the session bus */
+
+const NOTIFICATIONS: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+/// Let the notification server pick how long it stays
+const DEFAULT_TIMEOUT: i32 = -1;
+
+/// Hands notifications to a thread of its own, since bus calls block and callers hold the stores.
+/// The thread connects on the first notification and again after a failed one
+#[derive(Default)]
+pub struct DbusSink {
+    /// Bus address. Session bus of the user if not set
+    address: Option<String>,
+    worker: Mutex<Option<Sender<Notification>>>,
+}
+
+impl DbusSink {
+    pub fn with_address(address: &str) -> Self {
+        Self {
+            address: Some(address.to_owned()),
+            worker: Mutex::new(None),
+        }
+    }
+}
+
+impl NotificationSink for DbusSink {
+    fn notify(&self, notification: &Notification) -> Result<(), TrackerError> {
+        let mut worker = self.worker.lock().unwrap();
+        let tx = worker.get_or_insert_with(|| {
+            let (tx, rx) = channel();
+            let address = self.address.clone();
+            thread::spawn(move || run_worker(address, rx));
+            tx
+        });
+        if tx.send(notification.clone()).is_err() {
+            *worker = None;
+            return Err(TrackerError::Desktop(
+                "Notification thread is gone".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Shows notifications until the sink is dropped
+fn run_worker(address: Option<String>, rx: Receiver<Notification>) {
+    let mut conn: Option<Connection> = None;
+    for notification in rx {
+        if conn.is_none() {
+            match connect(address.as_deref()) {
+                Ok(c) => conn = Some(c),
+                Err(e) => {
+                    eprintln!("Couldn't connect to the notification service: {}", e);
+                    continue;
+                }
+            }
+        }
+        if let Err(e) = show(conn.as_ref().unwrap(), &notification) {
+            eprintln!("Couldn't show notification: {}", e);
+            conn = None;
+        }
+    }
+}
+
+fn connect(address: Option<&str>) -> zbus::Result<Connection> {
+    match address {
+        Some(address) => connection::Builder::address(address)?.build(),
+        None => Connection::session(),
+    }
+}
+
+fn show(conn: &Connection, notification: &Notification) -> zbus::Result<()> {
+    let hints: HashMap<&str, Value> = HashMap::new();
+    conn.call_method(
+        Some(NOTIFICATIONS),
+        NOTIFICATIONS_PATH,
+        Some(NOTIFICATIONS),
+        "Notify",
+        &(
+            "Tracker",
+            0u32,
+            "",
+            notification.title.as_str(),
+            notification.body.as_str(),
+            Vec::<&str>::new(),
+            hints,
+            DEFAULT_TIMEOUT,
+        ),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{BufRead, BufReader};
+    use std::path::PathBuf;
+    use std::process::{Child, Command, Stdio};
+    use std::time::Duration;
+
+    use zbus::blocking::MessageIterator;
+    use zbus::message::Type;
+    use zbus::zvariant::OwnedValue;
+
+    use crate::notify::NotificationKind;
+
+    use super::*;
+
+    /// Arguments of the `Notify` method
+    type NotifyArgs = (
+        String,
+        u32,
+        String,
+        String,
+        String,
+        Vec<String>,
+        HashMap<String, OwnedValue>,
+        i32,
+    );
+
+    /// Bus daemon and its socket, both gone on drop
+    struct Bus(Child, PathBuf);
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+            let _ = fs::remove_file(&self.1);
+        }
+    }
+
+    /// Private session bus, if `dbus-daemon` is installed
+    fn start_bus() -> Option<(Bus, String)> {
+        let socket = std::env::temp_dir().join(format!("tracker-bus-{}", std::process::id()));
+        let mut child = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .arg(format!("--address=unix:path={}", socket.display()))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let stdout = child.stdout.take();
+        let bus = Bus(child, socket);
+        let mut address = String::new();
+        BufReader::new(stdout?).read_line(&mut address).ok()?;
+        Some((bus, address.trim().to_owned()))
+    }
+
+    #[test]
+    fn sends_notify_call_to_the_bus() {
+        let Some((_bus, address)) = start_bus() else {
+            eprintln!("dbus-daemon is not available, skipping");
+            return;
+        };
+
+        /* Stand-in notification server owning the well-known name */
+        let server = connection::Builder::address(address.as_str())
+            .unwrap()
+            .name(NOTIFICATIONS)
+            .unwrap()
+            .build()
+            .unwrap();
+        let (tx, rx) = channel();
+        // Bus goes away when the test ends
+        thread::spawn(move || {
+            for msg in MessageIterator::from(&server).flatten() {
+                let header = msg.header();
+                if header.message_type() != Type::MethodCall
+                    || header.member().is_none_or(|m| m.as_str() != "Notify")
+                {
+                    continue;
+                }
+                let signature = msg.body().signature().to_string_no_parens();
+                let args: NotifyArgs = msg.body().deserialize().unwrap();
+                server.reply(&header, &1u32).unwrap();
+                tx.send((signature, args.0, args.3, args.4)).unwrap();
+            }
+        });
+
+        let sink = DbusSink::with_address(&address);
+        let notification = Notification::new(NotificationKind::Badge, "New badge", "Rare", 0);
+        sink.notify(&notification).unwrap();
+        sink.notify(&notification).unwrap();
+
+        let timeout = Duration::from_secs(5);
+        let (signature, app, summary, body) = rx.recv_timeout(timeout).unwrap();
+        assert_eq!(signature, "susssasa{sv}i");
+        assert_eq!(
+            (app.as_str(), summary.as_str(), body.as_str()),
+            ("Tracker", "New badge", "Rare")
+        );
+        assert!(rx.recv_timeout(timeout).is_ok());
+    }
+}
@@ -0,0 +1,204 @@
+#[cfg(target_os = "linux")]
+pub mod dbus;
+#[cfg(windows)]
+pub mod windows;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::config::use_config;
+use crate::error::TrackerError;
+
+/* Tells the user about earned badges and time limits wherever they are looking:
+ * the desktop, the app window or both. Every place is a sink */
+
+/// Toasts kept for the app window. Older ones are dropped
+const TOAST_CAPACITY: usize = 20;
+
+lazy_static! {
+    static ref TOASTS: Arc<ToastSink> = Arc::new(ToastSink::default());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    Badge,
+    LimitWarning,
+    LimitReached,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    /// Unix seconds
+    pub time: u64,
+}
+
+impl Notification {
+    pub fn new(kind: NotificationKind, title: &str, body: &str, time: u64) -> Self {
+        Self {
+            kind,
+            title: title.to_owned(),
+            body: body.to_owned(),
+            time,
+        }
+    }
+}
+
+/// Somewhere notifications are shown
+pub trait NotificationSink: Send + Sync {
+    fn notify(&self, notification: &Notification) -> Result<(), TrackerError>;
+}
+
+/// Sends every notification to all sinks. A failing sink doesn't stop the others
+#[derive(Clone, Default)]
+pub struct Notifier {
+    sinks: Vec<Arc<dyn NotificationSink>>,
+}
+
+impl Notifier {
+    pub fn new(sinks: Vec<Arc<dyn NotificationSink>>) -> Self {
+        Self { sinks }
+    }
+
+    pub fn send(&self, notification: Notification) {
+        println!(
+            "Notification: {} - {}",
+            notification.title, notification.body
+        );
+        for sink in &self.sinks {
+            if let Err(e) = sink.notify(&notification) {
+                eprintln!("Couldn't show notification: {}", e);
+            }
+        }
+    }
+}
+
+/// In-app toasts and desktop notifications when enabled in the config
+pub fn default_notifier() -> Notifier {
+    let mut sinks: Vec<Arc<dyn NotificationSink>> = vec![use_toasts()];
+    if use_config().desktop_notifications {
+        #[cfg(target_os = "linux")]
+        sinks.push(Arc::new(dbus::DbusSink::default()));
+        #[cfg(windows)]
+        sinks.push(Arc::new(windows::WinToastSink));
+    }
+    Notifier::new(sinks)
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Toast {
+    /// Grows with every toast, so readers can tell which ones they've seen
+    pub id: u64,
+    pub notification: Notification,
+}
+
+/// Keeps recent notifications for the app window to show
+#[derive(Default)]
+pub struct ToastSink {
+    toasts: Mutex<Toasts>,
+}
+
+#[derive(Default)]
+struct Toasts {
+    queue: VecDeque<Toast>,
+    /// Highest id added, even if dismissed since
+    last_id: u64,
+}
+
+impl Toasts {
+    fn push(&mut self, toast: Toast) {
+        if self.queue.len() == TOAST_CAPACITY {
+            self.queue.pop_front();
+        }
+        self.last_id = toast.id;
+        self.queue.push_back(toast);
+    }
+}
+
+impl ToastSink {
+    /// Toasts from newest to oldest
+    pub fn recent(&self) -> Vec<Toast> {
+        self.toasts
+            .lock()
+            .unwrap()
+            .queue
+            .iter()
+            .rev()
+            .cloned()
+            .collect()
+    }
+
+    /// Adds toasts made elsewhere, e.g. by the daemon, skipping the ones already seen
+    pub fn merge(&self, mut toasts: Vec<Toast>) {
+        let mut own = self.toasts.lock().unwrap();
+        toasts.sort_by_key(|t| t.id);
+        // Ids start over when the daemon restarts
+        if toasts.last().is_some_and(|t| t.id < own.last_id) {
+            own.last_id = 0;
+        }
+        for toast in toasts {
+            if toast.id > own.last_id {
+                own.push(toast);
+            }
+        }
+    }
+
+    /// Id of the newest toast so far, even if dismissed
+    pub fn last_id(&self) -> u64 {
+        self.toasts.lock().unwrap().last_id
+    }
+
+    pub fn dismiss(&self, id: u64) {
+        self.toasts.lock().unwrap().queue.retain(|t| t.id != id);
+    }
+}
+
+impl NotificationSink for ToastSink {
+    fn notify(&self, notification: &Notification) -> Result<(), TrackerError> {
+        let mut toasts = self.toasts.lock().unwrap();
+        let id = toasts.last_id + 1;
+        toasts.push(Toast {
+            id,
+            notification: notification.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// Toasts of this process
+pub fn use_toasts() -> Arc<ToastSink> {
+    TOASTS.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toasts_keep_order_and_merge_once() {
+        let sink = ToastSink::default();
+
+        for title in ["a", "b"] {
+            sink.notify(&Notification::new(NotificationKind::Badge, title, "", 0))
+                .unwrap();
+        }
+        let titles = |toasts: Vec<Toast>| -> Vec<String> {
+            toasts.into_iter().map(|t| t.notification.title).collect()
+        };
+        assert_eq!(titles(sink.recent()), vec!["b", "a"]);
+
+        let mirror = ToastSink::default();
+        mirror.merge(sink.recent());
+        mirror.merge(sink.recent());
+        assert_eq!(titles(mirror.recent()), vec!["b", "a"]);
+
+        mirror.dismiss(2);
+        mirror.merge(sink.recent());
+        assert_eq!(titles(mirror.recent()), vec!["a"]);
+    }
+}
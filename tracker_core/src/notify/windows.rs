@@ -0,0 +1,98 @@
+use std::ffi::OsStr;
+use std::io;
+use std::iter;
+use std::mem;
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+use std::thread;
+use std::time::Duration;
+
+use winapi::um::shellapi::{
+    Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_TIP, NIIF_INFO, NIM_ADD, NIM_DELETE, NOTIFYICONDATAW,
+};
+use winapi::um::winuser::{
+    CreateWindowExW, DestroyWindow, LoadIconW, HWND_MESSAGE, IDI_APPLICATION,
+};
+
+use crate::error::TrackerError;
+
+use super::{Notification, NotificationSink};
+
+/* Toasts through a tray icon balloon, which Windows 10 and later show as a toast */
+
+/// Long enough for the toast to show up before its icon goes away
+const ICON_LIFETIME: Duration = Duration::from_secs(10);
+
+pub struct WinToastSink;
+
+impl NotificationSink for WinToastSink {
+    fn notify(&self, notification: &Notification) -> Result<(), TrackerError> {
+        let title = notification.title.to_owned();
+        let body = notification.body.to_owned();
+        // Window and icon belong to the thread that created them, and have to outlive the toast
+        thread::spawn(move || {
+            if let Err(e) = show_balloon(&title, &body) {
+                eprintln!("Couldn't show toast: {}", e);
+            }
+        });
+        Ok(())
+    }
+}
+
+fn show_balloon(title: &str, body: &str) -> io::Result<()> {
+    let class: Vec<u16> = OsStr::new("STATIC")
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect();
+    // Message-only window, never shown
+    let hwnd = unsafe {
+        CreateWindowExW(
+            0,
+            class.as_ptr(),
+            ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+    };
+    if hwnd.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut data: NOTIFYICONDATAW = unsafe { mem::zeroed() };
+    data.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+    data.hWnd = hwnd;
+    data.uID = 1;
+    data.uFlags = NIF_ICON | NIF_TIP | NIF_INFO;
+    data.hIcon = unsafe { LoadIconW(ptr::null_mut(), IDI_APPLICATION) };
+    data.dwInfoFlags = NIIF_INFO;
+    copy_wide(&mut data.szTip, "Tracker");
+    copy_wide(&mut data.szInfoTitle, title);
+    copy_wide(&mut data.szInfo, body);
+
+    let result = if unsafe { Shell_NotifyIconW(NIM_ADD, &mut data) } != 0 {
+        thread::sleep(ICON_LIFETIME);
+        unsafe { Shell_NotifyIconW(NIM_DELETE, &mut data) };
+        Ok(())
+    } else {
+        Err(io::Error::other("Tray icon could not be added"))
+    };
+    unsafe { DestroyWindow(hwnd) };
+    result
+}
+
+/// Truncates to fit the fixed buffer along with the terminating zero
+fn copy_wide(dst: &mut [u16], value: &str) {
+    let wide: Vec<u16> = OsStr::new(value)
+        .encode_wide()
+        .take(dst.len() - 1)
+        .collect();
+    dst[..wide.len()].copy_from_slice(&wide);
+    dst[wide.len()] = 0;
+}
@@ -0,0 +1,337 @@
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::error::TrackerError;
+use crate::tracking::TrackLog;
+
+use super::{schema, StorageBackend};
+
+/// Number of backups kept next to the stats file
+pub const DEFAULT_BACKUPS: usize = 5;
+/// A new backup is made on write if the newest one is older than this
+pub const DEFAULT_BACKUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Whole-file JSON storage. Writes within one process are serialized.
+/// The file is replaced atomically and previous versions are kept as `<file>.<timestamp>.bak`.
+/// If the file can't be parsed, the newest valid backup is restored
+pub struct JsonStorage {
+    path: PathBuf,
+    backups: usize,
+    backup_interval: Duration,
+    lock: Mutex<()>,
+    notice: Mutex<Option<String>>,
+}
+
+impl JsonStorage {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            backups: DEFAULT_BACKUPS,
+            backup_interval: DEFAULT_BACKUP_INTERVAL,
+            lock: Mutex::new(()),
+            notice: Mutex::new(None),
+        }
+    }
+
+    /// Keeps up to `count` backups, made at most once per `interval`. Zero count disables backups
+    pub fn with_backups(mut self, count: usize, interval: Duration) -> Self {
+        self.backups = count;
+        self.backup_interval = interval;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.is_file()
+    }
+
+    /// Existing backups, oldest first
+    pub fn backup_files(&self) -> Result<Vec<PathBuf>, TrackerError> {
+        let prefix = format!("{}.", self.file_name());
+        let mut files: Vec<PathBuf> = match fs::read_dir(self.dir()) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with(&prefix) && n.ends_with(".bak"))
+                })
+                .collect(),
+            Err(e) if e.kind() == ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.into()),
+        };
+        // Timestamps have fixed width, so names sort by time
+        files.sort();
+        Ok(files)
+    }
+
+    fn read(&self) -> Result<Vec<TrackLog>, TrackerError> {
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        match parse_stats(&data) {
+            Ok(stats) if !data.trim().is_empty() => Ok(stats),
+            // Older versions could leave the file empty after a crash
+            Ok(stats) => Ok(self.recover()?.unwrap_or(stats)),
+            Err(e) => self.recover()?.ok_or(e),
+        }
+    }
+
+    /// Restores the newest backup that can be parsed. Damaged file is kept as `<file>.corrupt-<timestamp>`
+    fn recover(&self) -> Result<Option<Vec<TrackLog>>, TrackerError> {
+        for backup in self.backup_files()?.iter().rev() {
+            let Ok(data) = fs::read_to_string(backup) else {
+                continue;
+            };
+            let Ok(stats) = parse_stats(&data) else {
+                continue;
+            };
+            if data.trim().is_empty() {
+                continue;
+            }
+
+            let damaged = self.sibling(&format!("corrupt-{}", timestamp()));
+            fs::rename(&self.path, &damaged)?;
+            write_atomic(&self.path, data.as_bytes())?;
+
+            let notice = format!(
+                "{} was damaged and has been restored from {}. Damaged copy is kept at {}",
+                self.path.display(),
+                backup.display(),
+                damaged.display()
+            );
+            eprintln!("{}", notice);
+            *self.notice.lock().unwrap() = Some(notice);
+            return Ok(Some(stats));
+        }
+        Ok(None)
+    }
+
+    fn write(&self, stats: &[TrackLog]) -> Result<(), TrackerError> {
+        let serialized = schema::encode(stats)?;
+        if let Err(e) = self.backup() {
+            eprintln!("Couldn't back up {}: {}", self.path.display(), e);
+        }
+        write_atomic(&self.path, serialized.as_bytes())
+    }
+
+    /// Copies the current file aside if the newest backup is old enough and drops the oldest ones
+    fn backup(&self) -> Result<(), TrackerError> {
+        if self.backups == 0 || !self.exists() {
+            return Ok(());
+        }
+        let mut backups = self.backup_files()?;
+
+        let is_due = match backups.last() {
+            Some(newest) => fs::metadata(newest)?
+                .modified()?
+                .elapsed()
+                .map_or(true, |age| age >= self.backup_interval),
+            None => true,
+        };
+        if is_due {
+            let backup = self.sibling(&format!("{}.bak", timestamp()));
+            fs::copy(&self.path, &backup)?;
+            backups.push(backup);
+        }
+
+        while backups.len() > self.backups {
+            fs::remove_file(backups.remove(0))?;
+        }
+        Ok(())
+    }
+
+    fn dir(&self) -> &Path {
+        match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        }
+    }
+
+    fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map_or_else(String::new, |n| n.to_string_lossy().into_owned())
+    }
+
+    /// Path next to the stats file, named `<file>.<suffix>`
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        self.dir().join(format!("{}.{}", self.file_name(), suffix))
+    }
+}
+
+impl StorageBackend for JsonStorage {
+    fn load_all(&self) -> Result<Vec<TrackLog>, TrackerError> {
+        let _lock = self.lock.lock().unwrap();
+        self.read()
+    }
+
+    fn save(&self, log: &TrackLog) -> Result<(), TrackerError> {
+        let _lock = self.lock.lock().unwrap();
+        let mut stats = self.read()?;
+
+        match stats
+            .iter_mut()
+            .find(|curr| curr.process_name == log.process_name)
+        {
+            Some(curr) => {
+                curr.sessions = log.sessions.to_owned();
+                curr.set_display_name(&log.display_name);
+                curr.badges = log.badges.to_owned();
+                curr.rules = log.rules.to_owned();
+                curr.instances = log.instances.to_owned();
+                curr.paused = log.paused;
+                curr.limits = log.limits;
+                curr.is_running = false;
+            }
+            None => stats.push(log.clone()),
+        }
+        self.write(&stats)
+    }
+
+    fn delete(&self, log: &TrackLog) -> Result<(), TrackerError> {
+        let _lock = self.lock.lock().unwrap();
+        let mut stats = self.read()?;
+
+        stats.retain(|curr| curr.process_name != log.process_name);
+        self.write(&stats)
+    }
+
+    fn take_notice(&self) -> Option<String> {
+        self.notice.lock().unwrap().take()
+    }
+}
+
+/// Empty file is treated as an empty list, like the first versions of the app created it
+fn parse_stats(data: &str) -> Result<Vec<TrackLog>, TrackerError> {
+    if data.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    schema::decode(data)
+}
+
+/// Writes to a temporary file next to the target and renames it over, so readers
+/// see either the old or the new content, never a partial one
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), TrackerError> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp = PathBuf::from(tmp_name);
+
+    let mut file = File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Sortable UTC time with milliseconds
+fn timestamp() -> String {
+    Utc::now().format("%Y%m%dT%H%M%S%3f").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracking::Session;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tracker_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn log(proc_name: &str, duration: u64) -> TrackLog {
+        let mut log = TrackLog::new("alice", proc_name, proc_name);
+        let mut session = Session::new(100);
+        session.extend(100 + duration, duration);
+        log.update_session(session);
+        log
+    }
+
+    #[test]
+    fn keeps_rolling_backups() {
+        let dir = temp_dir("backups");
+        let json = JsonStorage::new(&dir.join("stats.json")).with_backups(3, Duration::ZERO);
+
+        for i in 1..=6 {
+            json.save(&log("game", i)).unwrap();
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        let backups = json.backup_files().unwrap();
+        assert_eq!(backups.len(), 3);
+        // Newest backup holds the state before the last write
+        let newest = fs::read_to_string(backups.last().unwrap()).unwrap();
+        assert_eq!(parse_stats(&newest).unwrap()[0].uptime(), 5);
+        assert_eq!(json.load_all().unwrap()[0].uptime(), 6);
+        assert!(!dir.join("stats.json.tmp").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recovers_from_newest_valid_backup() {
+        let dir = temp_dir("recover");
+        let path = dir.join("stats.json");
+        let json = JsonStorage::new(&path).with_backups(5, Duration::ZERO);
+
+        json.save(&log("game", 10)).unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        json.save(&log("game", 20)).unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        json.save(&log("game", 30)).unwrap();
+        // Newest backup is damaged as well
+        let backups = json.backup_files().unwrap();
+        fs::write(backups.last().unwrap(), "[{\"username\":").unwrap();
+        fs::write(&path, "[{\"username\": \"al").unwrap();
+
+        let stats = json.load_all().unwrap();
+        assert_eq!(stats[0].uptime(), 10);
+        assert!(json.take_notice().is_some());
+        assert!(json.take_notice().is_none());
+
+        // Restored file is valid and damaged one is kept aside
+        assert_eq!(
+            parse_stats(&fs::read_to_string(&path).unwrap())
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(fs::read_dir(&dir).unwrap().any(|e| e
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .contains(".corrupt-")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_overwrite_damaged_file() {
+        let dir = temp_dir("damaged");
+        let path = dir.join("stats.json");
+        fs::write(&path, "not json").unwrap();
+        let json = JsonStorage::new(&path);
+
+        assert!(json.load_all().is_err());
+        assert!(json.save(&log("game", 10)).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "not json");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,222 @@
+pub mod json;
+pub mod schema;
+pub mod sqlite;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::config::use_config;
+use crate::error::TrackerError;
+use crate::tracking::TrackLog;
+
+use self::{json::JsonStorage, sqlite::SqliteStorage};
+
+/* Persistence of tracked apps. SQLite is used by default, JSON file is kept as a fallback */
+
+pub const STATS_FILE: &str = "stats.json";
+pub const DB_FILE: &str = "stats.db";
+/// Write-ahead log and shared memory files SQLite keeps next to the database
+const DB_SIDECARS: [&str; 2] = ["-wal", "-shm"];
+/// Left in the data directory once files of older versions were looked for
+const MIGRATED_MARKER: &str = ".migrated";
+
+/// Env variable to pick the backend. Either `sqlite` (default) or `json`
+pub const STORAGE_ENV_VAR: &str = "TRACKER_STORAGE";
+
+pub trait StorageBackend: Send + Sync {
+    /// Track logs of every user
+    fn load_all(&self) -> Result<Vec<TrackLog>, TrackerError>;
+
+    /// Inserts the log or updates the stored one
+    fn save(&self, log: &TrackLog) -> Result<(), TrackerError>;
+
+    fn delete(&self, log: &TrackLog) -> Result<(), TrackerError>;
+
+    /// Message about a problem the backend has recovered from since the last call
+    fn take_notice(&self) -> Option<String> {
+        None
+    }
+
+    fn load_by_user(&self, username: &str) -> Result<Vec<TrackLog>, TrackerError> {
+        Ok(self
+            .load_all()?
+            .into_iter()
+            .filter(|p| p.username == username)
+            .collect())
+    }
+}
+
+/// Opens the backend chosen by [`STORAGE_ENV_VAR`] in the configured data directory.
+/// Falls back to JSON if the database can't be opened
+pub fn open_default_storage() -> Arc<dyn StorageBackend> {
+    let dir = use_config().data_dir();
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("Couldn't create data directory {}: {}", dir.display(), e);
+    }
+    if let Some(legacy_dir) = legacy_data_dir() {
+        for moved in migrate_once(&legacy_dir, &dir) {
+            println!("Moved {} to {}", moved.display(), dir.display());
+        }
+    }
+
+    let stats_path = dir.join(STATS_FILE);
+    let db_path = dir.join(DB_FILE);
+    let json = JsonStorage::new(&stats_path);
+
+    if env::var(STORAGE_ENV_VAR).is_ok_and(|v| v.eq_ignore_ascii_case("json")) {
+        return Arc::new(json);
+    }
+
+    match SqliteStorage::open(&db_path) {
+        Ok(db) => {
+            if json.exists() {
+                match db.import_legacy_json(&json) {
+                    Ok(0) => (),
+                    Ok(count) => println!("Imported {} apps from {}", count, stats_path.display()),
+                    Err(e) => eprintln!("Couldn't import {}: {}", stats_path.display(), e),
+                }
+                if let Some(notice) = json.take_notice() {
+                    db.set_notice(notice);
+                }
+            }
+            Arc::new(db)
+        }
+        Err(e) => {
+            eprintln!(
+                "Couldn't open database {}. Using {}: {}",
+                db_path.display(),
+                stats_path.display(),
+                e
+            );
+            Arc::new(json)
+        }
+    }
+}
+
+/// Older versions kept data in the working directory, which was where the app was started from
+pub fn legacy_data_dir() -> Option<PathBuf> {
+    env::current_exe().ok()?.parent().map(Path::to_path_buf)
+}
+
+/// [`migrate_data_dir`] the first time only, so files that turn up in `from` later stay there
+pub fn migrate_once(from: &Path, to: &Path) -> Vec<PathBuf> {
+    let marker = to.join(MIGRATED_MARKER);
+    if marker.exists() {
+        return vec![];
+    }
+    let moved = migrate_data_dir(from, to);
+    if let Err(e) = fs::write(&marker, "") {
+        eprintln!("Couldn't write {}: {}", marker.display(), e);
+    }
+    moved
+}
+
+/// Moves data files left in `from` into `to`. SQLite journal files go along with the database. Files that already exist in `to` are left alone.
+/// Returns the moved files
+pub fn migrate_data_dir(from: &Path, to: &Path) -> Vec<PathBuf> {
+    let same_dir = match (from.canonicalize(), to.canonicalize()) {
+        (Ok(from), Ok(to)) => from == to,
+        _ => false,
+    };
+    if same_dir {
+        return vec![];
+    }
+
+    let mut moved = vec![];
+    for name in [STATS_FILE, DB_FILE] {
+        let (src, dst) = (from.join(name), to.join(name));
+        if !src.is_file() || dst.exists() {
+            continue;
+        }
+        match move_file(&src, &dst) {
+            Ok(_) if name == DB_FILE => {
+                moved.push(src);
+                moved.extend(move_db_sidecars(from, to));
+            }
+            Ok(_) => moved.push(src),
+            Err(e) => eprintln!(
+                "Couldn't move {} to {}: {}",
+                src.display(),
+                dst.display(),
+                e
+            ),
+        }
+    }
+    moved
+}
+
+fn move_db_sidecars(from: &Path, to: &Path) -> Vec<PathBuf> {
+    let mut moved = vec![];
+    for suffix in DB_SIDECARS {
+        let name = format!("{}{}", DB_FILE, suffix);
+        let (src, dst) = (from.join(&name), to.join(&name));
+        if !src.is_file() {
+            continue;
+        }
+        match move_file(&src, &dst) {
+            Ok(_) => moved.push(src),
+            Err(e) => eprintln!("Couldn't move {}: {}", src.display(), e),
+        }
+    }
+    moved
+}
+
+/// Rename, or copy and remove if the directories are on different file systems
+fn move_file(src: &Path, dst: &Path) -> Result<(), TrackerError> {
+    if fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+    fs::copy(src, dst)?;
+    fs::remove_file(src)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_legacy_files_once() {
+        let root = env::temp_dir().join(format!("tracker_migrate_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (old, new) = (root.join("old"), root.join("new"));
+        fs::create_dir_all(&old).unwrap();
+        fs::create_dir_all(&new).unwrap();
+        fs::write(old.join(STATS_FILE), "[]").unwrap();
+        fs::write(old.join(DB_FILE), "old db").unwrap();
+        fs::write(new.join(DB_FILE), "new db").unwrap();
+
+        let moved = migrate_data_dir(&old, &new);
+        assert_eq!(moved, vec![old.join(STATS_FILE)]);
+        assert_eq!(fs::read_to_string(new.join(STATS_FILE)).unwrap(), "[]");
+        // Existing data is never overwritten
+        assert_eq!(fs::read_to_string(new.join(DB_FILE)).unwrap(), "new db");
+        assert!(old.join(DB_FILE).exists());
+
+        assert!(migrate_data_dir(&new, &new).is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn moves_database_with_journal_on_first_run_only() {
+        let root = env::temp_dir().join(format!("tracker_migrate_db_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (old, new) = (root.join("old"), root.join("new"));
+        fs::create_dir_all(&old).unwrap();
+        fs::create_dir_all(&new).unwrap();
+        fs::write(old.join(DB_FILE), "db").unwrap();
+        fs::write(old.join("stats.db-wal"), "wal").unwrap();
+
+        let moved = migrate_once(&old, &new);
+        assert_eq!(moved, vec![old.join(DB_FILE), old.join("stats.db-wal")]);
+        assert_eq!(fs::read_to_string(new.join("stats.db-wal")).unwrap(), "wal");
+
+        // Later files in the old place belong to something else
+        fs::write(old.join(STATS_FILE), "[]").unwrap();
+        assert!(migrate_once(&old, &new).is_empty());
+        assert!(old.join(STATS_FILE).exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::TrackerError;
+use crate::tracking::TrackLog;
+
+/* Versioned layout of the stats file. Older documents are upgraded one version at a time:
+ *  0 - bare array of apps with total `uptime` only
+ *  1 - bare array of apps with `sessions`
+ *  2 - `{ "schema_version": 2, "apps": [...] }`
+ *
+ * Fields added since (focused time, pause, rules, instances, limits, when badges were earned)
+ * are optional and default when missing, so documents without them are still version 2.
+ * The version only goes up when existing data has to be rewritten to be read */
+
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Upgrades a document from `from` to `from + 1`
+pub struct Migration {
+    pub from: u32,
+    pub migrate: fn(Value) -> Result<Value, TrackerError>,
+}
+
+/// Every step from the first format to [`SCHEMA_VERSION`], in order
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        migrate: uptime_to_sessions,
+    },
+    Migration {
+        from: 1,
+        migrate: wrap_in_envelope,
+    },
+];
+
+#[derive(Deserialize, Serialize)]
+struct Envelope {
+    schema_version: u32,
+    apps: Vec<TrackLog>,
+}
+
+/// Parses a stats document of any known version
+pub fn decode(data: &str) -> Result<Vec<TrackLog>, TrackerError> {
+    let mut doc: Value = serde_json::from_str(data)?;
+    let mut version = detect_version(&doc)?;
+
+    if version > SCHEMA_VERSION {
+        return Err(TrackerError::Parse(format!(
+            "Stats were written by a newer version of the app (schema {}, supported up to {})",
+            version, SCHEMA_VERSION
+        )));
+    }
+    while version < SCHEMA_VERSION {
+        let step = MIGRATIONS
+            .iter()
+            .find(|m| m.from == version)
+            .ok_or_else(|| TrackerError::Parse(format!("No migration from schema {}", version)))?;
+        doc = (step.migrate)(doc)?;
+        version += 1;
+    }
+
+    Ok(serde_json::from_value::<Envelope>(doc)?.apps)
+}
+
+/// Serializes apps in the current format
+pub fn encode(apps: &[TrackLog]) -> Result<String, TrackerError> {
+    Ok(serde_json::to_string_pretty(&json!({
+        "schema_version": SCHEMA_VERSION,
+        "apps": apps,
+    }))?)
+}
+
+fn detect_version(doc: &Value) -> Result<u32, TrackerError> {
+    match doc {
+        Value::Array(apps) => {
+            let has_sessions = apps.iter().any(|app| app.get("sessions").is_some());
+            Ok(if has_sessions || apps.is_empty() {
+                1
+            } else {
+                0
+            })
+        }
+        Value::Object(obj) => obj
+            .get("schema_version")
+            .and_then(Value::as_u64)
+            .map(|v| v as u32)
+            .ok_or_else(|| TrackerError::Parse("Stats file has no schema_version".to_owned())),
+        _ => Err(TrackerError::Parse("Unknown stats file format".to_owned())),
+    }
+}
+
+/// Turns the total uptime into a single legacy session
+fn uptime_to_sessions(mut doc: Value) -> Result<Value, TrackerError> {
+    let apps = doc
+        .as_array_mut()
+        .ok_or_else(|| TrackerError::Parse("Expected an array of apps".to_owned()))?;
+    for app in apps.iter_mut().filter_map(Value::as_object_mut) {
+        let uptime = app.get("uptime").and_then(Value::as_u64).unwrap_or(0);
+        let sessions = if uptime > 0 {
+            json!([{ "start": 0, "end": 0, "duration": uptime, "legacy": true }])
+        } else {
+            json!([])
+        };
+        app.insert("sessions".to_owned(), sessions);
+    }
+    Ok(doc)
+}
+
+fn wrap_in_envelope(doc: Value) -> Result<Value, TrackerError> {
+    Ok(json!({ "schema_version": 2, "apps": doc }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracking::Session;
+
+    use super::*;
+
+    const FIXTURES: &[(u32, &str)] = &[
+        (0, include_str!("../../fixtures/schema/v0.json")),
+        (1, include_str!("../../fixtures/schema/v1.json")),
+        (2, include_str!("../../fixtures/schema/v2.json")),
+    ];
+
+    #[test]
+    fn registry_covers_every_version() {
+        for (i, step) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(step.from, i as u32);
+        }
+        assert_eq!(MIGRATIONS.len() as u32, SCHEMA_VERSION);
+        assert_eq!(FIXTURES.last().unwrap().0, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn loads_every_historical_format() {
+        for (version, data) in FIXTURES {
+            let doc: Value = serde_json::from_str(data).unwrap();
+            assert_eq!(detect_version(&doc).unwrap(), *version);
+
+            let apps = decode(data).unwrap_or_else(|e| panic!("v{}: {}", version, e));
+            assert_eq!(apps.len(), 2, "v{}", version);
+            assert_eq!(apps[0].process_name, "game");
+            assert_eq!(apps[0].display_name, "Game");
+            assert_eq!(apps[0].uptime(), 7200, "v{}", version);
+            assert_eq!(apps[0].badges[0].id, "Initial");
+            assert_eq!(apps[1].uptime(), 0);
+
+            // Round trip keeps everything
+            let reloaded = decode(&encode(&apps).unwrap()).unwrap();
+            assert_eq!(reloaded[0].sessions, apps[0].sessions);
+        }
+        assert_eq!(
+            decode(FIXTURES[0].1).unwrap()[0].sessions,
+            vec![Session::legacy(7200)]
+        );
+    }
+
+    #[test]
+    fn reads_optional_fields_of_current_schema() {
+        let data = include_str!("../../fixtures/schema/v2_optional.json");
+        let apps = decode(data).unwrap();
+        let game = &apps[0];
+        assert_eq!(game.focused_time(), 1800);
+        assert!(game.paused);
+        assert_eq!(game.rules[0].pattern, "/opt/game/*");
+        assert_eq!(game.instances[0].pid, Some(4242));
+        assert_eq!(game.limits.weekly, Some(36000));
+        assert_eq!(game.badges[0].earned_at, Some(1700000000));
+
+        let reloaded = decode(&encode(&apps).unwrap()).unwrap();
+        let again = &reloaded[0];
+        assert_eq!(again.sessions, game.sessions);
+        assert_eq!(again.instances, game.instances);
+        assert_eq!(again.rules, game.rules);
+        assert_eq!(again.limits, game.limits);
+        assert_eq!(again.paused, game.paused);
+        assert_eq!(again.badges[0].earned_at, game.badges[0].earned_at);
+    }
+
+    #[test]
+    fn rejects_newer_schema() {
+        let data = r#"{ "schema_version": 99, "apps": [] }"#;
+        assert!(decode(data).is_err());
+    }
+}
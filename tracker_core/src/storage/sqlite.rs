@@ -0,0 +1,501 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde_json::Value;
+
+use crate::error::TrackerError;
+use crate::tracking::{badges::Badge, limits::UsageLimits, rules::TrackRule, Session, TrackLog};
+
+use super::{json::JsonStorage, StorageBackend};
+
+const SCHEMA: &str = "
+    PRAGMA foreign_keys = ON;
+    CREATE TABLE IF NOT EXISTS users (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE IF NOT EXISTS apps (
+        id INTEGER PRIMARY KEY,
+        user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        process_name TEXT NOT NULL,
+        display_name TEXT NOT NULL,
+        UNIQUE (user_id, process_name)
+    );
+    CREATE TABLE IF NOT EXISTS sessions (
+        id INTEGER PRIMARY KEY,
+        app_id INTEGER NOT NULL REFERENCES apps(id) ON DELETE CASCADE,
+        start INTEGER NOT NULL,
+        end INTEGER NOT NULL,
+        duration INTEGER NOT NULL,
+        legacy INTEGER NOT NULL DEFAULT 0,
+        UNIQUE (app_id, start, legacy)
+    );
+    CREATE TABLE IF NOT EXISTS badges (
+        id INTEGER PRIMARY KEY,
+        app_id INTEGER NOT NULL REFERENCES apps(id) ON DELETE CASCADE,
+        rank TEXT NOT NULL,
+        username TEXT NOT NULL,
+        description TEXT NOT NULL,
+        UNIQUE (app_id, rank)
+    );
+    CREATE TABLE IF NOT EXISTS rules (
+        id INTEGER PRIMARY KEY,
+        app_id INTEGER NOT NULL REFERENCES apps(id) ON DELETE CASCADE,
+        field TEXT NOT NULL,
+        syntax TEXT NOT NULL,
+        pattern TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+/// Changes made after the first release of [`SCHEMA`]. Database `user_version` is the number of applied steps
+const MIGRATIONS: &[&str] = &[
+    "ALTER TABLE sessions ADD COLUMN focused INTEGER NOT NULL DEFAULT 0;",
+    "CREATE TABLE instances (
+        id INTEGER PRIMARY KEY,
+        app_id INTEGER NOT NULL REFERENCES apps(id) ON DELETE CASCADE,
+        pid INTEGER NOT NULL,
+        proc_start INTEGER,
+        start INTEGER NOT NULL,
+        end INTEGER NOT NULL,
+        duration INTEGER NOT NULL,
+        UNIQUE (app_id, pid, start)
+    );",
+    "ALTER TABLE apps ADD COLUMN paused INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE apps ADD COLUMN daily_limit INTEGER;
+     ALTER TABLE apps ADD COLUMN weekly_limit INTEGER;",
+    "ALTER TABLE badges ADD COLUMN earned_at INTEGER;",
+];
+
+/// Marks that `stats.json` was already copied into the database
+const LEGACY_IMPORT_KEY: &str = "legacy_json_imported";
+
+/// Embedded SQLite database. Every save is a single transaction
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+    notice: Mutex<Option<String>>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<Self, TrackerError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, TrackerError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, TrackerError> {
+        conn.execute_batch(SCHEMA)?;
+        migrate(&conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            notice: Mutex::new(None),
+        })
+    }
+
+    /// Passes on a message from another storage, e.g. a JSON file restored during import
+    pub fn set_notice(&self, notice: String) {
+        *self.notice.lock().unwrap() = Some(notice);
+    }
+
+    /// Copies every app from the legacy JSON file once. Returns the number of imported apps
+    pub fn import_legacy_json(&self, json: &JsonStorage) -> Result<usize, TrackerError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let imported: Option<String> = tx
+            .query_row(
+                "SELECT value FROM meta WHERE key = ?1",
+                [LEGACY_IMPORT_KEY],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if imported.is_some() {
+            return Ok(0);
+        }
+
+        let logs = json.load_all()?;
+        for log in &logs {
+            save_log(&tx, log)?;
+        }
+        tx.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)",
+            params![LEGACY_IMPORT_KEY, json.path().to_string_lossy()],
+        )?;
+        tx.commit()?;
+
+        Ok(logs.len())
+    }
+}
+
+impl StorageBackend for SqliteStorage {
+    fn load_all(&self) -> Result<Vec<TrackLog>, TrackerError> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut logs: Vec<(i64, TrackLog)> = conn
+            .prepare(
+                "SELECT apps.id, users.name, apps.process_name, apps.display_name, apps.paused,
+                    apps.daily_limit, apps.weekly_limit
+                 FROM apps JOIN users ON users.id = apps.user_id ORDER BY apps.id",
+            )?
+            .query_map([], |row| {
+                let mut log = TrackLog::new(
+                    &row.get::<_, String>(1)?,
+                    &row.get::<_, String>(2)?,
+                    &row.get::<_, String>(3)?,
+                );
+                // Nothing is running until the supervisor sees it
+                log.is_running = false;
+                log.paused = row.get(4)?;
+                log.limits = UsageLimits {
+                    daily: row.get(5)?,
+                    weekly: row.get(6)?,
+                };
+                Ok((row.get(0)?, log))
+            })?
+            .collect::<Result<_, _>>()?;
+
+        let index: HashMap<i64, usize> = logs
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| (*id, i))
+            .collect();
+
+        let mut stmt = conn.prepare(
+            "SELECT app_id, start, end, duration, focused, legacy FROM sessions ORDER BY app_id, start",
+        )?;
+        let sessions = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                Session {
+                    start: row.get(1)?,
+                    end: row.get(2)?,
+                    duration: row.get(3)?,
+                    focused: row.get(4)?,
+                    legacy: row.get(5)?,
+                    pid: None,
+                    proc_start: None,
+                },
+            ))
+        })?;
+        for row in sessions {
+            let (app_id, session) = row?;
+            if let Some(i) = index.get(&app_id) {
+                logs[*i].1.sessions.push(session);
+            }
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT app_id, pid, proc_start, start, end, duration FROM instances ORDER BY app_id, start",
+        )?;
+        let instances = stmt.query_map([], |row| {
+            let mut session = Session::new(row.get(3)?).for_instance(row.get(1)?, row.get(2)?);
+            session.end = row.get(4)?;
+            session.duration = row.get(5)?;
+            Ok((row.get::<_, i64>(0)?, session))
+        })?;
+        for row in instances {
+            let (app_id, session) = row?;
+            if let Some(i) = index.get(&app_id) {
+                logs[*i].1.instances.push(session);
+            }
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT app_id, rank, username, description, earned_at FROM badges ORDER BY id",
+        )?;
+        let badges = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                Badge {
+                    id: row.get(1)?,
+                    username: row.get(2)?,
+                    description: row.get(3)?,
+                    earned_at: row.get(4)?,
+                },
+            ))
+        })?;
+        for row in badges {
+            let (app_id, badge) = row?;
+            if let Some(i) = index.get(&app_id) {
+                logs[*i].1.badges.push(badge);
+            }
+        }
+
+        let mut stmt =
+            conn.prepare("SELECT app_id, field, syntax, pattern FROM rules ORDER BY id")?;
+        let rules = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        for row in rules {
+            let (app_id, field, syntax, pattern) = row?;
+            if let Some(i) = index.get(&app_id) {
+                logs[*i].1.rules.push(TrackRule {
+                    field: serde_json::from_value(Value::String(field))?,
+                    syntax: serde_json::from_value(Value::String(syntax))?,
+                    pattern,
+                });
+            }
+        }
+
+        Ok(logs.into_iter().map(|(_, log)| log).collect())
+    }
+
+    fn save(&self, log: &TrackLog) -> Result<(), TrackerError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        save_log(&tx, log)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete(&self, log: &TrackLog) -> Result<(), TrackerError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM apps WHERE process_name = ?1
+             AND user_id = (SELECT id FROM users WHERE name = ?2)",
+            params![log.process_name, log.username],
+        )?;
+        Ok(())
+    }
+
+    fn take_notice(&self) -> Option<String> {
+        self.notice.lock().unwrap().take()
+    }
+}
+
+fn migrate(conn: &Connection) -> Result<(), TrackerError> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (i, step) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(&format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            step,
+            i + 1
+        ))?;
+    }
+    Ok(())
+}
+
+fn save_log(tx: &Transaction, log: &TrackLog) -> Result<(), TrackerError> {
+    tx.execute(
+        "INSERT INTO users (name) VALUES (?1) ON CONFLICT (name) DO NOTHING",
+        [&log.username],
+    )?;
+    let app_id: i64 = tx.query_row(
+        "INSERT INTO apps (user_id, process_name, display_name, paused, daily_limit, weekly_limit)
+         VALUES ((SELECT id FROM users WHERE name = ?1), ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT (user_id, process_name)
+         DO UPDATE SET display_name = excluded.display_name, paused = excluded.paused,
+            daily_limit = excluded.daily_limit, weekly_limit = excluded.weekly_limit
+         RETURNING id",
+        params![
+            log.username,
+            log.process_name,
+            log.display_name,
+            log.paused,
+            log.limits.daily,
+            log.limits.weekly
+        ],
+        |row| row.get(0),
+    )?;
+
+    // The log holds the whole history, so rows it dropped or moved are removed as well
+    for table in ["sessions", "instances", "badges"] {
+        tx.execute(
+            &format!("DELETE FROM {} WHERE app_id = ?1", table),
+            [app_id],
+        )?;
+    }
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO sessions (app_id, start, end, duration, focused, legacy)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT (app_id, start, legacy)
+         DO UPDATE SET end = excluded.end, duration = excluded.duration, focused = excluded.focused",
+    )?;
+    for s in &log.sessions {
+        stmt.execute(params![
+            app_id, s.start, s.end, s.duration, s.focused, s.legacy
+        ])?;
+    }
+
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO instances (app_id, pid, proc_start, start, end, duration)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT (app_id, pid, start)
+         DO UPDATE SET end = excluded.end, duration = excluded.duration",
+    )?;
+    for s in &log.instances {
+        stmt.execute(params![
+            app_id,
+            s.pid.unwrap_or_default(),
+            s.proc_start,
+            s.start,
+            s.end,
+            s.duration
+        ])?;
+    }
+
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO badges (app_id, rank, username, description, earned_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT (app_id, rank) DO UPDATE SET earned_at = excluded.earned_at",
+    )?;
+    for badge in &log.badges {
+        stmt.execute(params![
+            app_id,
+            badge.id,
+            badge.username,
+            badge.description,
+            badge.earned_at
+        ])?;
+    }
+
+    // Rules are edited as a whole, so the stored set is replaced
+    tx.execute("DELETE FROM rules WHERE app_id = ?1", [app_id])?;
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO rules (app_id, field, syntax, pattern) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for rule in &log.rules {
+        stmt.execute(params![
+            app_id,
+            enum_str(&rule.field)?,
+            enum_str(&rule.syntax)?,
+            rule.pattern
+        ])?;
+    }
+    Ok(())
+}
+
+/// Serde name of a unit enum variant
+fn enum_str<T: serde::Serialize>(value: &T) -> Result<String, TrackerError> {
+    Ok(match serde_json::to_value(value)? {
+        Value::String(s) => s,
+        other => other.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracking::badges::BadgeDefs;
+    use crate::tracking::rules::{RuleField, RuleSyntax};
+
+    use super::*;
+
+    fn log(username: &str, proc_name: &str, sessions: &[(u64, u64)]) -> TrackLog {
+        let mut log = TrackLog::new(username, proc_name, proc_name);
+        for (start, duration) in sessions {
+            let mut session = Session::new(*start);
+            session.extend(start + duration, *duration);
+            log.update_session(session);
+        }
+        log
+    }
+
+    #[test]
+    fn saves_and_loads_logs() {
+        let db = SqliteStorage::open_in_memory().unwrap();
+        let mut game = log("alice", "game", &[(100, 50), (1000, 20)]);
+        game.badges.push(
+            BadgeDefs::builtin()
+                .get("Initial")
+                .unwrap()
+                .badge("alice", 1000),
+        );
+        game.set_display_name("Game");
+        game.paused = true;
+        game.limits.daily = Some(3600);
+        game.rules = vec![TrackRule::new(
+            RuleField::Cmdline,
+            RuleSyntax::Regex,
+            "game\\.jar",
+        )];
+        let mut instance = Session::new(100).for_instance(42, Some(90));
+        instance.extend(130, 30);
+        game.update_instance(instance);
+        db.save(&game).unwrap();
+        db.save(&log("bob", "game", &[(5, 5)])).unwrap();
+
+        let loaded = db.load_by_user("alice").unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].display_name, "Game");
+        assert_eq!(loaded[0].limits.daily, Some(3600));
+        assert_eq!(loaded[0].limits.weekly, None);
+        assert!(loaded[0].paused);
+        assert_eq!(loaded[0].sessions, game.sessions);
+        assert_eq!(loaded[0].badges.len(), 1);
+        assert_eq!(loaded[0].badges[0].id, game.badges[0].id);
+        assert_eq!(loaded[0].badges[0].earned_at, Some(1000));
+        assert!(!loaded[0].is_running);
+        assert_eq!(loaded[0].rules, game.rules);
+        assert_eq!(loaded[0].instances, game.instances);
+        assert_eq!(db.load_all().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn replaces_history_on_save() {
+        let db = SqliteStorage::open_in_memory().unwrap();
+        let mut game = log("alice", "game", &[(100, 50), (1000, 20)]);
+        let mut instance = Session::new(100).for_instance(42, Some(90));
+        instance.extend(150, 50);
+        game.update_instance(instance);
+        game.badges.push(
+            BadgeDefs::builtin()
+                .get("Initial")
+                .unwrap()
+                .badge("alice", 1000),
+        );
+        db.save(&game).unwrap();
+
+        // Running session grows, the first one was rewound away and the badge got its real date
+        let mut game = log("alice", "game", &[(1000, 30)]);
+        let mut badge = BadgeDefs::builtin()
+            .get("Initial")
+            .unwrap()
+            .badge("alice", 1000);
+        badge.earned_at = Some(150);
+        game.badges.push(badge);
+        db.save(&game).unwrap();
+
+        let loaded = db.load_all().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].sessions, game.sessions);
+        assert_eq!(loaded[0].uptime(), 30);
+        assert!(loaded[0].instances.is_empty());
+        assert_eq!(loaded[0].badges.len(), 1);
+        assert_eq!(loaded[0].badges[0].earned_at, Some(150));
+    }
+
+    #[test]
+    fn deletes_app_with_history() {
+        let db = SqliteStorage::open_in_memory().unwrap();
+        let game = log("alice", "game", &[(100, 50)]);
+        db.save(&game).unwrap();
+        db.save(&log("alice", "editor", &[(100, 50)])).unwrap();
+        db.delete(&game).unwrap();
+
+        let loaded = db.load_all().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].process_name, "editor");
+
+        // Re-added app starts from scratch
+        db.save(&log("alice", "game", &[])).unwrap();
+        let game = db
+            .load_all()
+            .unwrap()
+            .into_iter()
+            .find(|l| l.process_name == "game")
+            .unwrap();
+        assert_eq!(game.uptime(), 0);
+    }
+}
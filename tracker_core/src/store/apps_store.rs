@@ -1,22 +1,42 @@
-use std::{
-    borrow::Borrow,
-    sync::{
-        mpsc::{channel, Receiver, Sender, TryRecvError},
-        Arc, Mutex,
-    },
-    thread,
+use std::sync::{
+    mpsc::{channel, Receiver, Sender, TryRecvError},
+    Arc, Mutex,
 };
 
 use crate::{
-    tracking::{badges::Badge, get_tracked_procs_by_user, start_tracking, TrackLog},
-    win_funcs::{get_running_procs, process::ProcessInfo},
+    error::TrackerError,
+    ipc::{remote, Client, Command, Snapshot},
+    notify::{use_toasts, Notification, NotificationKind},
+    sys::{watcher::ProcessEvent, ProcessInfo},
+    tracking::{
+        badges::{use_badge_defs, Badge},
+        import::merge_logs,
+        limits::{LimitAlert, LimitLevel, UsageLimits},
+        rules::{AppMatcher, LivePids, TrackRule},
+        start_tracking, unix_secs, use_tracking_env, Session, TrackLog, Tracker, TrackingEnv,
+    },
 };
 
-use super::{user_store::use_user_store, ReducerMsg, Store};
+use super::user_store::{use_user_store, UserActions};
+use super::{ReducerMsg, Store};
+
+pub type AppsStore = Arc<Mutex<Store<AppState, Actions>>>;
 
 lazy_static! {
-    static ref APPS_STORE: Arc<Mutex<Store<AppState, Actions>>> =
-        Arc::new(Mutex::new(Store::new(Box::new(reducer))));
+    static ref APPS_STORE: AppsStore = match remote() {
+        Some(client) => create_remote_apps_store(client),
+        None => create_apps_store(use_tracking_env()),
+    };
+}
+
+/// Creates a store whose tracking threads run in the given environment
+pub fn create_apps_store(env: TrackingEnv) -> AppsStore {
+    Arc::new_cyclic(|store| {
+        let tracker = Tracker::new(env, store.clone());
+        Mutex::new(Store::new(Box::new(move |state, msg| {
+            reducer(state, msg, &tracker)
+        })))
+    })
 }
 
 #[derive(Default)]
@@ -28,26 +48,96 @@ pub struct AppState {
     pub is_error_untracked: bool,
     pub is_error_tracked: bool,
     pub error: Option<String>,
-    tracked_tx: Option<Receiver<Vec<TrackLog>>>,
-    untracked_tx: Option<Receiver<Vec<ProcessInfo>>>,
+    /// Live pids of tracked apps matched on the last check. An app runs while any instance is alive
+    pub running_apps: LivePids,
+    /// Tracked app owning the foreground window
+    pub focused_app: Option<String>,
+    /// Limits reached in the current periods. Each fires once per period
+    pub limit_alerts: Vec<LimitAlert>,
+    /// Grows with every action. Stores mirroring the daemon keep the revision of the daemon
+    pub revision: u64,
+    /// Uptime of all tracked apps when the profile was last worked out
+    profiled_uptime: Option<u64>,
+    tracked_tx: Option<Receiver<Result<Vec<TrackLog>, TrackerError>>>,
+    untracked_tx: Option<Receiver<Result<Vec<ProcessInfo>, TrackerError>>>,
     channel_senders: Vec<ChannelSender>,
 }
 
-fn reducer(state: &mut AppState, msg: Actions) {
+/// Creates a store mirroring the daemon. Changes are sent to it and refresh the state,
+/// otherwise it's up to [`Actions::ApplySnapshot`]
+pub fn create_remote_apps_store(client: &'static Client) -> AppsStore {
+    Arc::new(Mutex::new(Store::new(Box::new(move |state, msg| {
+        remote_reducer(state, msg, client)
+    }))))
+}
+
+fn remote_reducer(state: &mut AppState, msg: Actions, client: &Client) {
+    match msg {
+        Actions::ApplySnapshot(snapshot) => return apply_snapshot(state, *snapshot),
+        Actions::ReportError(msg) => return state.error = Some(msg),
+        Actions::CleanErrorMsg => state.error = None,
+        _ => (),
+    }
+    // Reads don't wait for the daemon, the state is refreshed apart from the UI
+    let Some(command) = Command::from_action(&msg) else {
+        return;
+    };
+    if let Err(e) = client.send(command) {
+        state.error = Some(format!("Tracking daemon is unreachable: {}", e));
+        return;
+    }
+    match client.snapshot_since(Some(state.revision)) {
+        Ok(Some(snapshot)) => apply_snapshot(state, snapshot),
+        Ok(None) => (),
+        Err(e) => state.error = Some(format!("Tracking daemon is unreachable: {}", e)),
+    }
+}
+
+fn apply_snapshot(state: &mut AppState, snapshot: Snapshot) {
+    // Taken before a change this store has already seen
+    if snapshot.revision < state.revision {
+        return;
+    }
+    state.revision = snapshot.revision;
+    state.tracked_apps = snapshot.tracked_apps;
+    state.untracked_apps = snapshot.untracked_apps;
+    state.running_apps = snapshot.running_apps;
+    state.focused_app = snapshot.focused_app;
+    state.limit_alerts = snapshot.limit_alerts;
+    use_toasts().merge(snapshot.toasts);
+    use_user_store().dispatch(UserActions::SetProfile(Box::new(snapshot.profile)));
+    state.error = snapshot.error;
+    state.is_fetching_tracked = false;
+    state.is_fetching_untracked = false;
+    state.is_error_tracked = false;
+    state.is_error_untracked = false;
+}
+
+fn reducer(state: &mut AppState, msg: Actions, tracker: &Tracker) {
+    /* Storage may have repaired itself while handling a previous action */
+    if let Some(notice) = tracker.env.storage.take_notice() {
+        state.error = Some(notice);
+    }
+    if !matches!(msg, Actions::None) {
+        state.revision += 1;
+    }
     match msg {
         Actions::FetchTrackedApps => {
             if !state.is_fetching_tracked {
-                fetch_tracked_apps(state)
+                fetch_tracked_apps(state, tracker)
             } else if state.tracked_tx.is_some() {
                 match state.tracked_tx.as_ref().unwrap().try_recv() {
-                    Ok(data) => {
-                        if data.len() == 0 {
-                            state.is_error_tracked = true;
-                        } else {
-                            state.is_error_tracked = false;
-                        }
+                    Ok(Ok(data)) => {
+                        state.is_error_tracked = data.is_empty();
                         state.is_fetching_tracked = false;
                         state.tracked_apps = data;
+                        backfill_badges(state, tracker);
+                        update_profile(state, tracker);
+                    }
+                    Ok(Err(e)) => {
+                        state.is_error_tracked = true;
+                        state.is_fetching_tracked = false;
+                        report(state, "Couldn't load tracked apps", e);
                     }
                     Err(e) => {
                         if e == TryRecvError::Empty {
@@ -66,28 +156,26 @@ fn reducer(state: &mut AppState, msg: Actions) {
                 }
             }
 
-            let rx = start_tracking(&proc_name);
-            state
-                .tracked_apps
-                .push(TrackLog::new(&username, &proc_name, &proc_name));
+            let log = TrackLog::new(&username, &proc_name, &proc_name);
+            // Don't wait for the supervisor, or the new thread pauses right away
+            state.running_apps.extend(
+                AppMatcher::new(std::slice::from_ref(&log)).running_apps(&state.untracked_apps),
+            );
+            let rx = start_tracking(tracker, &proc_name);
+            state.tracked_apps.push(log);
             state
                 .channel_senders
                 .push(ChannelSender::new(&proc_name, rx));
         }
         Actions::SaveData(proc_name) => {
-            let mut tracked_log: Option<&mut TrackLog> = None;
-
-            for ind in 0..state.tracked_apps.len() {
-                if state.tracked_apps[ind].process_name == proc_name {
-                    tracked_log = Some(&mut state.tracked_apps[ind]);
-                    break;
-                }
+            if let Err(e) = find_app(&mut state.tracked_apps, &proc_name)
+                .and_then(|log| tracker.env.storage.save(log))
+            {
+                report(state, &format!("Cannot save {}", proc_name), e);
             }
-            match tracked_log {
-                Some(log) => {
-                    log.save_to_file();
-                }
-                _ => eprintln!("Cannot save tracked progress: {}. Not found.", proc_name),
+            let uptime = state.tracked_apps.iter().map(|l| l.uptime()).sum();
+            if state.profiled_uptime != Some(uptime) {
+                update_profile(state, tracker);
             }
         }
         Actions::DeleteTrackedApp(proc_name) => {
@@ -99,23 +187,23 @@ fn reducer(state: &mut AppState, msg: Actions) {
                 }
             }
             /* Clean listener channel */
-            if rx.is_some() {
-                rx.unwrap().send("Terminate".to_owned());
+            if let Some(rx) = rx {
+                if rx.send("Terminate".to_owned()).is_err() {
+                    let e = TrackerError::NotFound("Tracking thread".to_owned());
+                    report(state, &format!("Cannot stop tracking {}", proc_name), e);
+                }
             };
 
             /* Delete all data from file */
             for i in 0..state.tracked_apps.len() {
                 if state.tracked_apps[i].process_name == proc_name {
-                    match state.tracked_apps[i].delete_from_file() {
+                    match tracker.env.storage.delete(&state.tracked_apps[i]) {
                         Ok(_) => {
                             state.tracked_apps.remove(i);
                             break;
                         }
                         Err(e) => {
-                            println!(
-                                "Failed to delete track log {} from file. Reason: {}",
-                                proc_name, e
-                            );
+                            report(state, &format!("Cannot delete {}", proc_name), e);
                             break;
                         }
                     }
@@ -124,14 +212,19 @@ fn reducer(state: &mut AppState, msg: Actions) {
         }
         Actions::FetchUntrackedApps => {
             if !state.is_fetching_untracked {
-                fetch_untracked_apps(state);
+                fetch_untracked_apps(state, tracker);
             } else if state.untracked_tx.is_some() {
                 match state.untracked_tx.as_ref().unwrap().try_recv() {
-                    Ok(data) => {
+                    Ok(Ok(data)) => {
                         state.untracked_apps = data;
                         state.is_error_untracked = false;
                         state.is_fetching_untracked = false;
                     }
+                    Ok(Err(e)) => {
+                        state.is_error_untracked = true;
+                        state.is_fetching_untracked = false;
+                        report(state, "Couldn't list running apps", e);
+                    }
                     Err(e) => {
                         if e == TryRecvError::Empty {
                             state.is_error_untracked = true;
@@ -143,61 +236,74 @@ fn reducer(state: &mut AppState, msg: Actions) {
             }
         }
         Actions::AddBadgeToProc(badge, proc_name) => {
-            let mut log: Option<&mut TrackLog> = None;
-
-            for i in 0..state.tracked_apps.len() {
-                if state.tracked_apps[i].process_name == proc_name {
-                    log = Some(&mut state.tracked_apps[i]);
-                    break;
-                }
-            }
-            let log = log.unwrap();
-            let is_added = log.badges.iter().find(|b| b.rank == badge.rank).is_some();
+            // Tracking thread of a deleted app may still send updates
+            let log = match find_app(&mut state.tracked_apps, &proc_name) {
+                Ok(log) => log,
+                Err(e) => return eprintln!("Cannot add badge {}: {}", badge.id, e),
+            };
+            let is_added = log.badges.iter().any(|b| b.id == badge.id);
 
             if !is_added {
+                let now = unix_secs(tracker.env.clock.now());
+                tracker.env.notifications.send(Notification::new(
+                    NotificationKind::Badge,
+                    &format!("New badge: {}", badge.id),
+                    &format!("{}. {}", log.display_name, badge.description),
+                    now,
+                ));
                 log.badges.push(badge);
             }
         }
 
-        Actions::UpdateAppTime(proc_name, secs) => {
-            let mut tracked_log: Option<&mut TrackLog> = None;
-
-            for ind in 0..state.tracked_apps.len() {
-                if state.tracked_apps[ind].process_name == proc_name {
-                    tracked_log = Some(&mut state.tracked_apps[ind]);
-                    break;
-                }
+        Actions::UpdateSession(proc_name, session) => {
+            match find_app(&mut state.tracked_apps, &proc_name) {
+                Ok(log) => log.update_session(session),
+                Err(e) => eprintln!("Cannot update session: {}", e),
             }
-            match tracked_log {
-                Some(log) => {
-                    log.set_uptime(secs);
-                }
-                _ => eprintln!("Cannot update: {}. Not found.", proc_name),
+        }
+        Actions::UpdateInstance(proc_name, session) => {
+            match find_app(&mut state.tracked_apps, &proc_name) {
+                Ok(log) => log.update_instance(session),
+                Err(e) => eprintln!("Cannot update instance: {}", e),
             }
         }
         Actions::ChangeTrackedAppName(proc_name, new_display_name) => {
-            let mut tracked_log: Option<&mut TrackLog> = None;
+            match find_app(&mut state.tracked_apps, &proc_name) {
+                Ok(log) => log.set_display_name(&new_display_name),
+                Err(e) => report(state, "Cannot change display name", e),
+            }
+        }
 
-            for ind in 0..state.tracked_apps.len() {
-                if state.tracked_apps[ind].process_name == proc_name {
-                    tracked_log = Some(&mut state.tracked_apps[ind]);
-                    break;
-                }
+        Actions::SetTrackingRules(proc_name, rules) => {
+            if let Err(e) = find_app(&mut state.tracked_apps, &proc_name).and_then(|log| {
+                log.rules = rules;
+                tracker.env.storage.save(log)
+            }) {
+                report(state, &format!("Cannot set rules of {}", proc_name), e);
             }
-            match tracked_log {
-                Some(log) => log.set_display_name(&new_display_name),
-                _ => eprintln!("Cannot change display name: {}. Not found", proc_name),
+        }
+        Actions::UpdateRunningApps(running) => state.running_apps = running,
+        Actions::UpdateFocusedApp(focused) => state.focused_app = focused,
+        Actions::SetPaused(proc_name, paused) => {
+            let log = match find_app(&mut state.tracked_apps, &proc_name) {
+                Ok(log) => log,
+                Err(e) => return report(state, "Cannot pause", e),
+            };
+            log.paused = paused;
+            if paused {
+                // Dropping the sender stops the tracking thread
+                state.channel_senders.retain(|s| s.proc_name != proc_name);
+                log.is_running = false;
+            }
+            // Supervisor resumes the app once it sees it running
+            if let Err(e) = tracker.env.storage.save(log) {
+                report(state, &format!("Cannot save {}", proc_name), e);
             }
         }
-
         Actions::PauseTracking(proc_name) => {
             println!("Pause tracking: {}", proc_name);
             /* Clear channel listener */
-            for ind in 0..state.channel_senders.len() {
-                if state.channel_senders[ind].proc_name == proc_name {
-                    state.channel_senders.remove(ind);
-                };
-            }
+            state.channel_senders.retain(|s| s.proc_name != proc_name);
             /* Update running status in tracklog for UI */
             for ind in 0..state.tracked_apps.len() {
                 let app = &mut state.tracked_apps[ind];
@@ -211,7 +317,7 @@ fn reducer(state: &mut AppState, msg: Actions) {
             for i in 0..state.tracked_apps.len() {
                 let app = &mut state.tracked_apps[i];
                 if app.process_name == proc_name {
-                    let rx = start_tracking(&app.process_name);
+                    let rx = start_tracking(tracker, &app.process_name);
                     app.is_running = true;
                     state
                         .channel_senders
@@ -221,36 +327,167 @@ fn reducer(state: &mut AppState, msg: Actions) {
             }
         }
         Actions::SaveAllData => {
-            if state.tracked_apps.len() == 0 {
+            if state.tracked_apps.is_empty() {
                 return;
             };
 
-            for i in 0..state.tracked_apps.len() {
-                let app = &state.tracked_apps[i];
-                match app.save_to_file() {
-                    Ok(_) => (),
-                    Err(e) => println!("Error saving data for {}. Reason:{}", app.process_name, e),
+            let mut failed = vec![];
+            let mut last_error = None;
+            for app in &state.tracked_apps {
+                if let Err(e) = tracker.env.storage.save(app) {
+                    failed.push(app.process_name.to_owned());
+                    last_error = Some(e);
                 }
             }
+            if let Some(e) = last_error {
+                report(state, &format!("Cannot save {}", failed.join(", ")), e);
+            }
+            update_profile(state, tracker);
         }
-        Actions::QueryUntrackedApps => {
-            state.untracked_apps = get_running_procs().unwrap_or(vec![]);
+        Actions::ProcessEvents(events) => {
+            for event in events {
+                match event {
+                    ProcessEvent::Exited(proc) => {
+                        state.untracked_apps.retain(|p| p.pid != proc.pid)
+                    }
+                    ProcessEvent::Started(proc) => {
+                        state.untracked_apps.retain(|p| p.pid != proc.pid);
+                        state.untracked_apps.push(proc);
+                    }
+                }
+            }
+        }
+        Actions::ImportApps(apps) => {
+            let (username, mode) = {
+                let mut user_store = use_user_store();
+                let user = user_store.selector();
+                (user.username.to_owned(), user.tracking_mode)
+            };
+            let now = unix_secs(tracker.env.clock.now());
+            let summary = merge_logs(&mut state.tracked_apps, apps, &username, mode, now);
+            let mut last_error = None;
+            for log in state
+                .tracked_apps
+                .iter()
+                .filter(|l| summary.changed.contains(&l.process_name))
+            {
+                if let Err(e) = tracker.env.storage.save(log) {
+                    last_error = Some((log.process_name.to_owned(), e));
+                }
+            }
+            if let Some((proc_name, e)) = last_error {
+                report(state, &format!("Cannot save imported {}", proc_name), e);
+            }
+        }
+        Actions::SetLimits(proc_name, limits) => {
+            // New limits are checked from scratch
+            state.limit_alerts.retain(|a| a.process_name != proc_name);
+            if let Err(e) = find_app(&mut state.tracked_apps, &proc_name).and_then(|log| {
+                log.limits = limits;
+                tracker.env.storage.save(log)
+            }) {
+                report(state, &format!("Cannot set limits of {}", proc_name), e);
+            }
+        }
+        Actions::AddLimitAlert(alert) => {
+            if state.limit_alerts.iter().any(|a| a.is_same(&alert)) {
+                return;
+            }
+            // Alerts of a past day or week are done with
+            state.limit_alerts.retain(|a| {
+                a.process_name != alert.process_name
+                    || a.period != alert.period
+                    || a.since == alert.since
+            });
+            let kind = match alert.level {
+                LimitLevel::Warning => NotificationKind::LimitWarning,
+                LimitLevel::Reached => NotificationKind::LimitReached,
+            };
+            let now = unix_secs(tracker.env.clock.now());
+            tracker.env.notifications.send(Notification::new(
+                kind,
+                "Time limit",
+                &alert.message(),
+                now,
+            ));
+            state.limit_alerts.push(alert);
+        }
+        Actions::DismissLimitAlerts => {
+            for alert in state.limit_alerts.iter_mut() {
+                alert.dismissed = true;
+            }
         }
         Actions::CleanErrorMsg => state.error = None,
+        Actions::ReportError(msg) => {
+            eprintln!("{}", msg);
+            state.error = Some(msg);
+        }
+        // Only stores mirroring the daemon take its state
+        Actions::ApplySnapshot(_) => (),
         Actions::None => (),
     };
 }
 
-fn fetch_untracked_apps(state: &mut AppState) {
-    let (rx, tx) = channel();
-    thread::spawn(move || match get_running_procs() {
-        Ok(procs) => {
-            if let Err(e) = rx.send(procs) {
-                eprint!("Error sending Untracked AppList: {}", e);
-            };
+/// Tracked app by its process name
+fn find_app<'a>(
+    apps: &'a mut [TrackLog],
+    proc_name: &str,
+) -> Result<&'a mut TrackLog, TrackerError> {
+    apps.iter_mut()
+        .find(|l| l.process_name == proc_name)
+        .ok_or_else(|| TrackerError::NotFound(format!("Tracked app {}", proc_name)))
+}
+
+/// Keeps the latest error for the UI, which clears it with [`Actions::CleanErrorMsg`]
+fn report(state: &mut AppState, context: &str, e: TrackerError) {
+    let msg = format!("{}: {}", context, e);
+    eprintln!("{}", msg);
+    state.error = Some(msg);
+}
+
+/// Profile goes over the whole history, so it's worked out when apps are loaded, all saved or their time changed.
+/// On its own thread, which doesn't hold this store while the user store works
+fn update_profile(state: &mut AppState, tracker: &Tracker) {
+    state.profiled_uptime = Some(state.tracked_apps.iter().map(|l| l.uptime()).sum());
+    let apps = state.tracked_apps.clone();
+    let notifications = tracker.env.notifications.clone();
+    tracker.spawn(move || {
+        use_user_store().dispatch(UserActions::UpdateProfile(apps, notifications));
+    });
+}
+
+/// Gives loaded apps the badges they reached while nothing was checking, e.g. with older versions.
+/// Quietly, since they'd all pop up at once
+fn backfill_badges(state: &mut AppState, tracker: &Tracker) {
+    let (username, mode) = {
+        let mut user_store = use_user_store();
+        let user = user_store.selector();
+        (user.username.to_owned(), user.tracking_mode)
+    };
+    let now = unix_secs(tracker.env.clock.now());
+    let mut last_error = None;
+    for log in state.tracked_apps.iter_mut() {
+        let badges = use_badge_defs().missed_badges(log, mode, &username, now);
+        if badges.is_empty() {
+            continue;
+        }
+        println!("Backfilled {} badges of {}", badges.len(), log.process_name);
+        log.badges.extend(badges);
+        if let Err(e) = tracker.env.storage.save(log) {
+            last_error = Some((log.process_name.to_owned(), e));
         }
-        Err(e) => {
-            eprint!("Couldn't get running processes: {}", e);
+    }
+    if let Some((proc_name, e)) = last_error {
+        report(state, &format!("Cannot save badges of {}", proc_name), e);
+    }
+}
+
+fn fetch_untracked_apps(state: &mut AppState, tracker: &Tracker) {
+    let (rx, tx) = channel();
+    let source = tracker.env.source.clone();
+    tracker.spawn(move || {
+        if rx.send(source.running_procs()).is_err() {
+            eprintln!("Untracked apps were fetched after the store was gone");
         }
     });
     state.untracked_tx = Some(tx);
@@ -258,29 +495,21 @@ fn fetch_untracked_apps(state: &mut AppState) {
     state.is_error_untracked = false;
 }
 
-fn fetch_tracked_apps(state: &mut AppState) {
+fn fetch_tracked_apps(state: &mut AppState, tracker: &Tracker) {
     let (rx, tx) = channel();
-    thread::spawn(
-        move || match get_tracked_procs_by_user(&use_user_store().selector().username) {
-            Ok(tracked_procs) => {
-                if let Err(e) = rx.send(tracked_procs) {
-                    eprintln!("Error sending Tracked AppList: {}", e);
-                };
-            }
-            Err(e) => {
-                eprintln!("Couldn't get tracked processes: {}", e);
-                if let Err(e) = rx.send(vec![]) {
-                    eprint!("Error sending Tracked AppList: {}", e);
-                };
-            }
-        },
-    );
+    let storage = tracker.env.storage.clone();
+    tracker.spawn(move || {
+        let tracked = storage.load_by_user(&use_user_store().selector().username);
+        if rx.send(tracked).is_err() {
+            eprintln!("Tracked apps were fetched after the store was gone");
+        }
+    });
     state.tracked_tx = Some(tx);
     state.is_fetching_tracked = true;
     state.is_error_tracked = false;
 }
 
-pub fn use_apps_store() -> Arc<Mutex<Store<AppState, Actions>>> {
+pub fn use_apps_store() -> AppsStore {
     APPS_STORE.clone()
 }
 
@@ -306,16 +535,37 @@ pub enum Actions {
     FetchTrackedApps,
     FetchUntrackedApps,
     AddTrackedApp(String, String),
-    UpdateAppTime(String, u64),
+    UpdateSession(String, Session),
     DeleteTrackedApp(String),
     SaveData(String),
     CleanErrorMsg,
+    /// Error found outside the store, shown like the ones of the store
+    ReportError(String),
+    /// State of the daemon for a store mirroring it
+    ApplySnapshot(Box<Snapshot>),
     SaveAllData,
     ChangeTrackedAppName(String, String),
-    QueryUntrackedApps,
+    /// Replaces the rules of a tracked app
+    SetTrackingRules(String, Vec<TrackRule>),
+    /// Result of matching running processes against tracking rules
+    UpdateRunningApps(LivePids),
+    /// Session of a single process of a tracked app
+    UpdateInstance(String, Session),
+    UpdateFocusedApp(Option<String>),
+    /// Processes that started or exited since the last check
+    ProcessEvents(Vec<ProcessEvent>),
     PauseTracking(String),
+    /// User stops or restarts tracking of an app regardless of its process
+    SetPaused(String, bool),
     ResumeTracking(String),
     AddBadgeToProc(Badge, String),
+    /// Usage history from another machine or tracker, merged into tracked apps and saved
+    ImportApps(Vec<TrackLog>),
+    /// Replaces the daily and weekly limits of a tracked app
+    SetLimits(String, UsageLimits),
+    AddLimitAlert(LimitAlert),
+    /// Hides banners of every alert so far
+    DismissLimitAlerts,
 }
 impl ReducerMsg for Actions {
     type Value = Actions;
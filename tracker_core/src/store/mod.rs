@@ -1,10 +1,13 @@
 pub mod apps_store;
 pub mod user_store;
 /* This implementaion rejects the idea of pure functions. Instead of copying state we simply mutate it in place */
+pub type Reducer<T, M> = Box<dyn Fn(&mut T, M)>;
+pub type Middleware<T, M> = Box<dyn Fn(&mut T, M) -> M>;
+
 pub struct Store<T, M> {
     state: T,
-    reducer: Box<dyn Fn(&mut T, M) -> ()>,
-    middleware: Vec<Box<dyn Fn(&mut T, M) -> M>>,
+    reducer: Reducer<T, M>,
+    middleware: Vec<Middleware<T, M>>,
 }
 
 unsafe impl<T, M> Send for Store<T, M> {}
@@ -14,7 +17,7 @@ where
     T: Default,
     M: ReducerMsg + Clone,
 {
-    pub fn new(reducer: Box<dyn Fn(&mut T, M) -> ()>) -> Self {
+    pub fn new(reducer: Reducer<T, M>) -> Self {
         Store {
             state: T::default(),
             reducer,
@@ -27,7 +30,7 @@ where
         self.middleware_strategy(msg.to_owned());
     }
     /* Adds middileware */
-    pub fn use_middleware(&mut self, mw: Box<dyn Fn(&mut T, M) -> M>) {
+    pub fn use_middleware(&mut self, mw: Middleware<T, M>) {
         self.middleware.push(mw);
     }
 
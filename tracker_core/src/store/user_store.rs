@@ -1,6 +1,14 @@
+use std::path::PathBuf;
 use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
 
-use crate::win_funcs::user::get_username;
+use crate::config::{update_config, use_config};
+use crate::error::TrackerError;
+use crate::ipc::{remote, Command};
+use crate::notify::{Notification, NotificationKind, Notifier};
+use crate::sys::get_username;
+use crate::tracking::profile::{profile_path, UserProfile};
+use crate::tracking::{unix_secs, TrackLog, TrackingMode};
 
 use super::{ReducerMsg, Store};
 
@@ -12,6 +20,13 @@ lazy_static! {
 pub struct UserState {
     pub username: String,
     pub is_logged: bool,
+    pub tracking_mode: TrackingMode,
+    /// Achievements over every tracked app
+    pub profile: UserProfile,
+    /// Where the profile is kept. It isn't saved before [`UserActions::InitConfig`] sets it
+    profile_path: Option<PathBuf>,
+    /// Latest error, shown next to the ones of the apps store
+    pub error: Option<String>,
 }
 
 impl Default for UserState {
@@ -19,6 +34,10 @@ impl Default for UserState {
         UserState {
             username: "".to_string(),
             is_logged: false,
+            tracking_mode: TrackingMode::default(),
+            profile: UserProfile::default(),
+            profile_path: None,
+            error: None,
         }
     }
 }
@@ -55,11 +74,64 @@ fn reducer(state: &mut UserState, msg: UserActions) {
         UserActions::InitConfig => {
             state.is_logged = false;
             state.init_username();
+            state.tracking_mode = use_config().tracking_mode;
+            let path = profile_path();
+            match UserProfile::load(&path) {
+                Ok(profile) => state.profile = profile,
+                Err(e) => report(state, "Couldn't read profile", e),
+            }
+            state.profile_path = Some(path);
+        }
+        UserActions::UpdateProfile(apps, notifier) => {
+            let saved = (
+                state.profile.achievements.clone(),
+                state.profile.favourites.clone(),
+            );
+            let now = unix_secs(SystemTime::now());
+            let earned = state.profile.update(&apps, state.tracking_mode, now);
+            for achievement in earned {
+                notifier.send(Notification::new(
+                    NotificationKind::Badge,
+                    &format!("New achievement: {}", achievement.id),
+                    achievement.description,
+                    now,
+                ));
+            }
+            let changed = saved
+                != (
+                    state.profile.achievements.clone(),
+                    state.profile.favourites.clone(),
+                );
+            if let Some(path) = state.profile_path.as_ref().filter(|_| changed) {
+                if let Err(e) = state.profile.save(path) {
+                    report(state, "Couldn't save profile", e);
+                }
+            }
         }
+        UserActions::SetProfile(profile) => state.profile = *profile,
+        UserActions::SetTrackingMode(mode) => {
+            state.tracking_mode = mode;
+            // Daemon keeps the config
+            if let Some(client) = remote() {
+                if let Err(e) = client.send(Command::SetTrackingMode { mode }) {
+                    report(state, "Couldn't change tracking mode of the daemon", e);
+                }
+            } else if let Err(e) = update_config(|config| config.tracking_mode = mode) {
+                report(state, "Couldn't save tracking mode", e);
+            }
+        }
+        UserActions::CleanErrorMsg => state.error = None,
         _ => (),
     }
 }
 
+/// Keeps the latest error for the UI, which clears it with [`UserActions::CleanErrorMsg`]
+fn report(state: &mut UserState, context: &str, e: TrackerError) {
+    let msg = format!("{}: {}", context, e);
+    eprintln!("{}", msg);
+    state.error = Some(msg);
+}
+
 pub fn use_user_store() -> MutexGuard<'static, Store<UserState, UserActions>> {
     USER_STORE.lock().unwrap()
 }
@@ -69,6 +141,12 @@ pub enum UserActions {
     None,
     ChangeUsername(String),
     InitConfig,
+    SetTrackingMode(TrackingMode),
+    /// Works out achievements over the given apps, the tracked ones of the user. New ones go out through the notifier
+    UpdateProfile(Vec<TrackLog>, Notifier),
+    /// Profile worked out by the daemon
+    SetProfile(Box<UserProfile>),
+    CleanErrorMsg,
 }
 
 impl ReducerMsg for UserActions {
@@ -0,0 +1,136 @@
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/* Time source of the tracking engine. Real one sleeps the thread, manual one is driven by tests */
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+    fn sleep(&self, dur: Duration);
+    /// Called by the engine before it spawns a thread that uses this clock
+    fn thread_started(&self) {}
+    /// Called by the engine when such thread finishes
+    fn thread_finished(&self) {}
+}
+
+#[derive(Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, dur: Duration) {
+        thread::sleep(dur)
+    }
+}
+
+/// How long [`ManualClock::advance`] waits for engine threads to settle before giving up
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Deterministic clock. Time moves only when [`ManualClock::advance`] is called.
+/// Sleeping threads are woken one by one in order of their deadlines,
+/// and the clock waits until every registered thread is asleep again before moving on.
+pub struct ManualClock {
+    state: Mutex<ManualState>,
+    cond: Condvar,
+}
+
+struct ManualState {
+    start: SystemTime,
+    elapsed: Duration,
+    threads: usize,
+    next_ticket: u64,
+    /// (deadline, ticket) of every sleeping thread
+    sleepers: Vec<(Duration, u64)>,
+    released: Option<u64>,
+}
+
+impl ManualClock {
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            state: Mutex::new(ManualState {
+                start,
+                elapsed: Duration::ZERO,
+                threads: 0,
+                next_ticket: 0,
+                sleepers: vec![],
+                released: None,
+            }),
+            cond: Condvar::new(),
+        }
+    }
+
+    /// Time passed since the clock was created
+    pub fn elapsed(&self) -> Duration {
+        self.state.lock().unwrap().elapsed
+    }
+
+    /// Moves time forward waking up every thread whose sleep ends within `dur`
+    pub fn advance(&self, dur: Duration) {
+        let mut state = self.settle(self.state.lock().unwrap());
+        let target = state.elapsed + dur;
+
+        loop {
+            let next = state.sleepers.iter().min().copied();
+            match next {
+                Some((deadline, ticket)) if deadline <= target => {
+                    state.elapsed = state.elapsed.max(deadline);
+                    state.released = Some(ticket);
+                    self.cond.notify_all();
+                    state = self.settle(state);
+                }
+                _ => break,
+            }
+        }
+        state.elapsed = target;
+    }
+
+    /// Blocks until every registered thread is sleeping
+    fn settle<'a>(&self, mut state: MutexGuard<'a, ManualState>) -> MutexGuard<'a, ManualState> {
+        let started = Instant::now();
+        while state.released.is_some() || state.sleepers.len() < state.threads {
+            if started.elapsed() > SETTLE_TIMEOUT {
+                panic!("Engine threads did not settle in {:?}", SETTLE_TIMEOUT);
+            }
+            state = self
+                .cond
+                .wait_timeout(state, Duration::from_millis(50))
+                .unwrap()
+                .0;
+        }
+        state
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        let state = self.state.lock().unwrap();
+        state.start + state.elapsed
+    }
+
+    fn sleep(&self, dur: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        let deadline = state.elapsed + dur;
+        state.sleepers.push((deadline, ticket));
+        self.cond.notify_all();
+
+        while state.released != Some(ticket) {
+            state = self.cond.wait(state).unwrap();
+        }
+        state.released = None;
+        state.sleepers.retain(|(_, t)| *t != ticket);
+    }
+
+    fn thread_started(&self) {
+        self.state.lock().unwrap().threads += 1;
+    }
+
+    fn thread_finished(&self) {
+        self.state.lock().unwrap().threads -= 1;
+        self.cond.notify_all();
+    }
+}
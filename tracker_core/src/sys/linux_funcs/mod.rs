@@ -0,0 +1,100 @@
+pub mod netlink;
+pub mod process;
+pub mod user;
+pub mod x11;
+
+use std::fs;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::{mem, ptr, thread};
+
+use crate::error::TrackerError;
+
+use super::{ProcessInfo, ProcessSource};
+
+use self::process::{read_boot_time, read_proc};
+
+const PROC_ROOT: &str = "/proc";
+
+/// Lists processes by reading `/proc/<pid>/comm`, `cmdline` and `stat`.
+pub struct ProcFsSource {
+    root: PathBuf,
+}
+
+impl ProcFsSource {
+    pub fn new(root: &str) -> Self {
+        Self {
+            root: PathBuf::from(root),
+        }
+    }
+}
+
+impl Default for ProcFsSource {
+    fn default() -> Self {
+        Self::new(PROC_ROOT)
+    }
+}
+
+impl ProcessSource for ProcFsSource {
+    fn running_procs(&self) -> Result<Vec<ProcessInfo>, TrackerError> {
+        enum_procs(&self.root).map_err(|e| TrackerError::ProcessAccess(e.to_string()))
+    }
+}
+
+fn enum_procs(root: &Path) -> std::io::Result<Vec<ProcessInfo>> {
+    let mut opened: u32 = 0;
+    let mut processes = Vec::new();
+    let boot_time = read_boot_time(root).ok();
+
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let pid = match entry
+            .file_name()
+            .to_str()
+            .and_then(|n| n.parse::<u32>().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        match read_proc(&entry.path(), pid, boot_time) {
+            Ok(Some(proc)) => {
+                processes.push(proc);
+                opened += 1;
+            }
+            // Kernel threads and zombies are not apps
+            Ok(None) => opened += 1,
+            // Process exited while we were reading it or we lack permissions
+            Err(_) => (),
+        }
+    }
+    if opened == 0 {
+        return Err(Error::other(
+            "App couldn't read any process. Check that /proc is mounted and readable",
+        ));
+    }
+    Ok(processes)
+}
+
+pub fn hide_console_window() {
+    // Linux apps are not attached to a console window unless started from a terminal
+}
+
+/// Runs `handler` on its own thread once the process gets SIGTERM, SIGINT or SIGHUP.
+/// Call it before starting other threads, they inherit the blocked signals
+pub fn on_termination(handler: impl FnOnce() + Send + 'static) {
+    let signals = unsafe {
+        let mut signals: libc::sigset_t = mem::zeroed();
+        libc::sigemptyset(&mut signals);
+        for signal in [libc::SIGTERM, libc::SIGINT, libc::SIGHUP] {
+            libc::sigaddset(&mut signals, signal);
+        }
+        libc::pthread_sigmask(libc::SIG_BLOCK, &signals, ptr::null_mut());
+        signals
+    };
+    thread::spawn(move || {
+        let mut signal = 0;
+        if unsafe { libc::sigwait(&signals, &mut signal) } == 0 {
+            handler();
+        }
+    });
+}
@@ -0,0 +1,151 @@
+use std::io::{Error, Result};
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::{Duration, Instant};
+
+use crate::sys::ProcessNotifier;
+
+/* Kernel process events through the netlink proc connector. Needs CAP_NET_ADMIN,
+ * so most users end up with plain polling */
+
+const NETLINK_CONNECTOR: i32 = 11;
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+
+const PROC_EVENT_EXEC: u32 = 0x0000_0002;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+const NLMSG_HDR_LEN: usize = 16;
+const CN_MSG_LEN: usize = 20;
+/// `what`, `cpu` and `timestamp_ns` of `struct proc_event`
+const PROC_EVENT_HDR_LEN: usize = 16;
+
+/// Wakes up when a program is executed or a process (not a thread) exits.
+pub struct NetlinkNotifier {
+    socket: OwnedFd,
+}
+
+impl NetlinkNotifier {
+    pub fn open() -> Result<Self> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                NETLINK_CONNECTOR,
+            )
+        };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as u16;
+        addr.nl_pid = 0;
+        addr.nl_groups = CN_IDX_PROC;
+        if unsafe {
+            libc::bind(
+                socket.as_raw_fd(),
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as u32,
+            )
+        } < 0
+        {
+            return Err(Error::last_os_error());
+        }
+
+        let notifier = Self { socket };
+        notifier.subscribe()?;
+        Ok(notifier)
+    }
+
+    fn subscribe(&self) -> Result<()> {
+        let len = NLMSG_HDR_LEN + CN_MSG_LEN + 4;
+        let mut msg = Vec::with_capacity(len);
+        // nlmsghdr
+        msg.extend_from_slice(&(len as u32).to_ne_bytes());
+        msg.extend_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+        msg.extend_from_slice(&0u16.to_ne_bytes());
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        msg.extend_from_slice(&std::process::id().to_ne_bytes());
+        // cn_msg
+        msg.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        msg.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        msg.extend_from_slice(&4u16.to_ne_bytes());
+        msg.extend_from_slice(&0u16.to_ne_bytes());
+        msg.extend_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+
+        let sent =
+            unsafe { libc::send(self.socket.as_raw_fd(), msg.as_ptr().cast(), msg.len(), 0) };
+        if sent < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Reads everything queued. True if any message was about an exec or a process exit
+    fn drain(&self) -> bool {
+        let mut buf = [0u8; 4096];
+        let mut changed = false;
+        loop {
+            let len = unsafe {
+                libc::recv(
+                    self.socket.as_raw_fd(),
+                    buf.as_mut_ptr().cast(),
+                    buf.len(),
+                    0,
+                )
+            };
+            if len <= 0 {
+                // Queue overflow also ends up here. Rescanning catches up anyway
+                return changed;
+            }
+            changed |= is_relevant(&buf[..len as usize]);
+        }
+    }
+}
+
+fn is_relevant(msg: &[u8]) -> bool {
+    let read_u32 = |at: usize| -> Option<u32> {
+        Some(u32::from_ne_bytes(msg.get(at..at + 4)?.try_into().ok()?))
+    };
+    let event = NLMSG_HDR_LEN + CN_MSG_LEN;
+    let data = event + PROC_EVENT_HDR_LEN;
+
+    match read_u32(event) {
+        Some(PROC_EVENT_EXEC) => true,
+        // Thread exits report pid != tgid
+        Some(PROC_EVENT_EXIT) => read_u32(data) == read_u32(data + 4),
+        _ => false,
+    }
+}
+
+impl ProcessNotifier for NetlinkNotifier {
+    fn wait(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return false;
+            }
+            let mut pfd = libc::pollfd {
+                fd: self.socket.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let ready =
+                unsafe { libc::poll(&mut pfd, 1, left.as_millis().min(i32::MAX as u128) as i32) };
+            if ready < 0 {
+                // Interrupted or broken socket. Behave like a plain sleep
+                std::thread::sleep(left);
+                return false;
+            }
+            if ready > 0 && self.drain() {
+                return true;
+            }
+        }
+    }
+}
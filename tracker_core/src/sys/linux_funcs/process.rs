@@ -0,0 +1,123 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::sys::ProcessInfo;
+
+/// Max length of a process name kept by the kernel in `comm`
+const COMM_LEN: usize = 15;
+
+/// Reads a single `/proc/<pid>` entry. Returns `None` for kernel threads and zombies.
+/// Start time is only known with the boot time from `/proc/stat`
+pub fn read_proc(dir: &Path, pid: u32, boot_time: Option<u64>) -> Result<Option<ProcessInfo>> {
+    let stat = ProcStat::parse(&fs::read_to_string(dir.join("stat"))?)?;
+    if stat.state == 'Z' {
+        return Ok(None);
+    }
+
+    let cmdline = read_cmdline(&fs::read(dir.join("cmdline"))?);
+    // Kernel threads have no command line
+    if cmdline.is_empty() {
+        return Ok(None);
+    }
+
+    let comm = match fs::read_to_string(dir.join("comm")) {
+        Ok(comm) => comm.trim_end_matches('\n').to_owned(),
+        Err(_) => stat.comm.to_owned(),
+    };
+
+    // Only readable for our own processes
+    let exe = fs::read_link(dir.join("exe"))
+        .ok()
+        .map(|path| path.to_string_lossy().into_owned());
+
+    Ok(Some(
+        ProcessInfo::new(&full_name(&comm, &cmdline), pid)
+            .with_cmdline(cmdline)
+            .with_exe(exe)
+            .with_start_time(boot_time.and_then(|boot| stat.start_time(boot))),
+    ))
+}
+
+/// Unix seconds of the system boot, `btime` line of `/proc/stat`
+pub fn read_boot_time(root: &Path) -> Result<u64> {
+    fs::read_to_string(root.join("stat"))?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|secs| secs.trim().parse().ok())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "No boot time in stat"))
+}
+
+fn clock_ticks_per_sec() -> u64 {
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as u64,
+        // Value the kernel reports on every common architecture
+        _ => 100,
+    }
+}
+
+/// `comm` is truncated by the kernel, so try to restore the full name from the executable path
+fn full_name(comm: &str, cmdline: &[String]) -> String {
+    if comm.len() < COMM_LEN {
+        return comm.to_owned();
+    }
+    let exe_name = cmdline
+        .first()
+        .and_then(|exe| Path::new(exe).file_name())
+        .and_then(|name| name.to_str());
+
+    match exe_name {
+        Some(name) if name.starts_with(comm) => name.to_owned(),
+        _ => comm.to_owned(),
+    }
+}
+
+fn read_cmdline(raw: &[u8]) -> Vec<String> {
+    raw.split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect()
+}
+
+/// Fields of `/proc/<pid>/stat` we care about
+pub struct ProcStat {
+    pub comm: String,
+    pub state: char,
+    /// Fields after the state, starting with ppid
+    pub rest: Vec<String>,
+}
+
+impl ProcStat {
+    pub fn parse(stat: &str) -> Result<Self> {
+        // comm is wrapped in parens and may contain spaces or parens itself
+        let (open, close) = match (stat.find('('), stat.rfind(')')) {
+            (Some(open), Some(close)) if open < close => (open, close),
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Malformed stat file")),
+        };
+        let mut fields = stat[close + 1..].split_whitespace();
+        let state = fields
+            .next()
+            .and_then(|s| s.chars().next())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Missing process state"))?;
+
+        Ok(Self {
+            comm: stat[open + 1..close].to_owned(),
+            state,
+            rest: fields.map(|f| f.to_owned()).collect(),
+        })
+    }
+
+    /// `starttime` field, in clock ticks after boot
+    pub fn start_ticks(&self) -> Option<u64> {
+        self.rest.get(18)?.parse().ok()
+    }
+
+    pub fn start_time(&self, boot_time: u64) -> Option<SystemTime> {
+        let ticks = self.start_ticks()?;
+        let per_sec = clock_ticks_per_sec();
+        let since_boot = Duration::from_secs(ticks / per_sec)
+            + Duration::from_secs(ticks % per_sec) / per_sec as u32;
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(boot_time) + since_boot)
+    }
+}
@@ -0,0 +1,25 @@
+use std::env;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::os::unix::fs::MetadataExt;
+
+/// Get current logon username
+pub fn get_username() -> std::io::Result<String> {
+    for var in ["USER", "LOGNAME"] {
+        if let Ok(name) = env::var(var) {
+            if !name.trim().is_empty() {
+                return Ok(name);
+            }
+        }
+    }
+
+    // Fallback to the owner of our own process
+    let uid = fs::metadata("/proc/self")?.uid();
+    let passwd = fs::read_to_string("/etc/passwd")?;
+    passwd
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<&str>>())
+        .find(|fields| fields.len() > 2 && fields[2] == uid.to_string())
+        .map(|fields| fields[0].to_owned())
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "No passwd entry for current user"))
+}
@@ -0,0 +1,103 @@
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use x11rb::connection::Connection;
+use x11rb::protocol::screensaver::ConnectionExt as _;
+use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt as _, Window};
+use x11rb::rust_connection::RustConnection;
+
+use crate::error::TrackerError;
+use crate::sys::{FocusSource, IdleSource};
+
+/// Reads the focused window (`_NET_ACTIVE_WINDOW` and its `_NET_WM_PID`) and
+/// the time since last input (XScreenSaver) from the X server.
+/// Connects lazily and reconnects after errors, so the app starts without a display.
+#[derive(Default)]
+pub struct X11Source {
+    conn: Mutex<Option<X11Conn>>,
+}
+
+struct X11Conn {
+    conn: RustConnection,
+    root: Window,
+    active_window: Atom,
+    wm_pid: Atom,
+}
+
+impl X11Conn {
+    fn connect() -> Result<Self, Box<dyn Error>> {
+        let (conn, screen) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen].root;
+        let active_window = conn
+            .intern_atom(false, b"_NET_ACTIVE_WINDOW")?
+            .reply()?
+            .atom;
+        let wm_pid = conn.intern_atom(false, b"_NET_WM_PID")?.reply()?.atom;
+        Ok(Self {
+            conn,
+            root,
+            active_window,
+            wm_pid,
+        })
+    }
+
+    fn focused_pid(&self) -> Result<Option<u32>, Box<dyn Error>> {
+        let window = self
+            .conn
+            .get_property(false, self.root, self.active_window, AtomEnum::WINDOW, 0, 1)?
+            .reply()?
+            .value32()
+            .and_then(|mut v| v.next());
+        let window = match window {
+            Some(window) if window != 0 => window,
+            _ => return Ok(None),
+        };
+
+        Ok(self
+            .conn
+            .get_property(false, window, self.wm_pid, AtomEnum::CARDINAL, 0, 1)?
+            .reply()?
+            .value32()
+            .and_then(|mut v| v.next()))
+    }
+
+    fn idle_time(&self) -> Result<Duration, Box<dyn Error>> {
+        let info = self.conn.screensaver_query_info(self.root)?.reply()?;
+        Ok(Duration::from_millis(info.ms_since_user_input as u64))
+    }
+}
+
+impl X11Source {
+    fn with_conn<T>(
+        &self,
+        f: impl FnOnce(&X11Conn) -> Result<T, Box<dyn Error>>,
+    ) -> Result<T, TrackerError> {
+        let mut conn = self.conn.lock().unwrap();
+        let result = match conn.take() {
+            Some(c) => Ok(c),
+            None => X11Conn::connect(),
+        }
+        .and_then(|c| {
+            let result = f(&c);
+            // Reconnect after errors
+            if result.is_ok() {
+                *conn = Some(c);
+            }
+            result
+        });
+        result.map_err(|e| TrackerError::Desktop(format!("X11: {}", e)))
+    }
+}
+
+impl FocusSource for X11Source {
+    fn focused_pid(&self) -> Result<Option<u32>, TrackerError> {
+        self.with_conn(X11Conn::focused_pid)
+    }
+}
+
+impl IdleSource for X11Source {
+    fn idle_time(&self) -> Result<Duration, TrackerError> {
+        self.with_conn(X11Conn::idle_time)
+    }
+}
@@ -0,0 +1,138 @@
+pub mod clock;
+#[cfg(target_os = "linux")]
+pub mod linux_funcs;
+pub mod scripted;
+pub mod watcher;
+#[cfg(windows)]
+pub mod win_funcs;
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::TrackerError;
+
+#[cfg(target_os = "linux")]
+pub use self::linux_funcs::{
+    hide_console_window, on_termination, user::get_username, x11::X11Source, ProcFsSource,
+};
+#[cfg(windows)]
+pub use self::win_funcs::{
+    hide_console_window, input::WinInputSource, on_termination, user::get_username,
+    WinProcessSource,
+};
+
+/* Platform-neutral access to the list of running processes. Implementation is picked at build time */
+
+#[cfg(target_os = "linux")]
+pub type PlatformProcessSource = ProcFsSource;
+#[cfg(windows)]
+pub type PlatformProcessSource = WinProcessSource;
+/// Focused window and user input
+#[cfg(target_os = "linux")]
+pub type PlatformInputSource = X11Source;
+#[cfg(windows)]
+pub type PlatformInputSource = WinInputSource;
+
+lazy_static! {
+    static ref PROCESS_SOURCE: Arc<dyn ProcessSource> = Arc::new(PlatformProcessSource::default());
+    static ref INPUT_SOURCE: Arc<PlatformInputSource> = Arc::new(PlatformInputSource::default());
+    static ref PROCESS_NOTIFIER: Arc<dyn ProcessNotifier> = open_process_notifier();
+}
+
+/// Anything that can list processes currently running in the system.
+pub trait ProcessSource: Send + Sync {
+    fn running_procs(&self) -> Result<Vec<ProcessInfo>, TrackerError>;
+}
+
+pub fn use_process_source() -> Arc<dyn ProcessSource> {
+    PROCESS_SOURCE.clone()
+}
+
+/// Lets the process watcher know that something has changed before the next scheduled check.
+pub trait ProcessNotifier: Send + Sync {
+    /// Blocks until a process starts or exits, or the timeout passes. True if woken by a change
+    fn wait(&self, timeout: Duration) -> bool;
+}
+
+pub fn use_process_notifier() -> Arc<dyn ProcessNotifier> {
+    PROCESS_NOTIFIER.clone()
+}
+
+/// Netlink proc connector when permitted, waiting out the timeout otherwise
+fn open_process_notifier() -> Arc<dyn ProcessNotifier> {
+    #[cfg(target_os = "linux")]
+    match linux_funcs::netlink::NetlinkNotifier::open() {
+        Ok(notifier) => return Arc::new(notifier),
+        Err(e) => println!("Process events unavailable, polling instead: {}", e),
+    }
+    Arc::new(watcher::ClockNotifier(Arc::new(clock::SystemClock)))
+}
+
+/// Anything that can tell which process owns the window the user is working in.
+pub trait FocusSource: Send + Sync {
+    /// `None` if no window is focused or its owner is unknown
+    fn focused_pid(&self) -> Result<Option<u32>, TrackerError>;
+}
+
+pub fn use_focus_source() -> Arc<dyn FocusSource> {
+    INPUT_SOURCE.clone()
+}
+
+/// Anything that can tell how long the user hasn't touched keyboard or mouse.
+pub trait IdleSource: Send + Sync {
+    fn idle_time(&self) -> Result<Duration, TrackerError>;
+}
+
+pub fn use_idle_source() -> Arc<dyn IdleSource> {
+    INPUT_SOURCE.clone()
+}
+
+pub fn get_running_procs() -> Result<Vec<ProcessInfo>, TrackerError> {
+    use_process_source().running_procs()
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ProcessInfo {
+    pub name: String,
+    pub pid: u32,
+    /// Full command line. First item is usually the executable path. Empty if unavailable.
+    pub cmdline: Vec<String>,
+    /// Full path of the executable, if the system lets us read it
+    pub exe: Option<String>,
+    /// When the OS created the process, if known. Tells apart processes that reuse a pid
+    pub started_at: Option<SystemTime>,
+}
+
+impl ProcessInfo {
+    pub fn new(name: &str, pid: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            pid,
+            cmdline: vec![],
+            exe: None,
+            started_at: None,
+        }
+    }
+
+    pub fn with_cmdline(mut self, cmdline: Vec<String>) -> Self {
+        self.cmdline = cmdline;
+        self
+    }
+
+    pub fn with_exe(mut self, exe: Option<String>) -> Self {
+        self.exe = exe;
+        self
+    }
+
+    pub fn with_start_time(mut self, started_at: Option<SystemTime>) -> Self {
+        self.started_at = started_at;
+        self
+    }
+
+    /// Same process, not just the same pid
+    pub fn is_same(&self, other: &ProcessInfo) -> bool {
+        self.pid == other.pid && self.name == other.name && self.started_at == other.started_at
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::error::TrackerError;
+
+use super::{clock::Clock, FocusSource, IdleSource, ProcessInfo, ProcessSource};
+
+/// Fake process list, window focus and user input for tests. Every process is visible between the ticks it was scripted with.
+/// Ticks are whole seconds passed on the given clock since the source was created.
+pub struct ScriptedProcessSource {
+    clock: Arc<dyn Clock>,
+    start: SystemTime,
+    procs: Mutex<Vec<ScriptedProc>>,
+    /// Focus changes as (tick, pid), in order
+    focus: Mutex<Vec<(u64, Option<u32>)>>,
+    /// Periods without input as (from, until)
+    away: Mutex<Vec<(u64, Option<u64>)>>,
+}
+
+struct ScriptedProc {
+    info: ProcessInfo,
+    appears_at: u64,
+    exits_at: Option<u64>,
+}
+
+impl ScriptedProcessSource {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            start: clock.now(),
+            clock,
+            procs: Mutex::new(vec![]),
+            focus: Mutex::new(vec![]),
+            away: Mutex::new(vec![]),
+        }
+    }
+
+    /// Process shows up at tick `appears_at` and is gone from tick `exits_at` on. `None` runs forever.
+    pub fn spawn_at(&self, info: ProcessInfo, appears_at: u64, exits_at: Option<u64>) -> &Self {
+        self.procs.lock().unwrap().push(ScriptedProc {
+            info,
+            appears_at,
+            exits_at,
+        });
+        self
+    }
+
+    /// Window of `pid` gets focus at tick `at`. `None` leaves nothing focused
+    pub fn focus_at(&self, pid: Option<u32>, at: u64) -> &Self {
+        let mut focus = self.focus.lock().unwrap();
+        focus.push((at, pid));
+        focus.sort_by_key(|(tick, _)| *tick);
+        drop(focus);
+        self
+    }
+
+    /// No input from tick `from` until tick `until`. `None` never comes back
+    pub fn away_at(&self, from: u64, until: Option<u64>) -> &Self {
+        self.away.lock().unwrap().push((from, until));
+        self
+    }
+
+    pub fn current_tick(&self) -> u64 {
+        self.clock
+            .now()
+            .duration_since(self.start)
+            .map_or(0, |d| d.as_secs())
+    }
+}
+
+impl ProcessSource for ScriptedProcessSource {
+    fn running_procs(&self) -> Result<Vec<ProcessInfo>, TrackerError> {
+        let tick = self.current_tick();
+        Ok(self
+            .procs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|p| p.appears_at <= tick && p.exits_at.is_none_or(|end| tick < end))
+            .map(|p| p.info.clone())
+            .collect())
+    }
+}
+
+impl FocusSource for ScriptedProcessSource {
+    fn focused_pid(&self) -> Result<Option<u32>, TrackerError> {
+        let tick = self.current_tick();
+        Ok(self
+            .focus
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(at, _)| *at <= tick)
+            .and_then(|(_, pid)| *pid))
+    }
+}
+
+impl IdleSource for ScriptedProcessSource {
+    fn idle_time(&self) -> Result<Duration, TrackerError> {
+        let tick = self.current_tick();
+        let idle = self
+            .away
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(from, until)| *from <= tick && until.is_none_or(|end| tick < end))
+            .map(|(from, _)| tick - from)
+            .max()
+            .unwrap_or(0);
+        Ok(Duration::from_secs(idle))
+    }
+}
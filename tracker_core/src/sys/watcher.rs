@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::TrackerError;
+
+use super::clock::Clock;
+use super::{ProcessInfo, ProcessNotifier, ProcessSource};
+
+/* Turns process list snapshots into start and exit events. A notifier, if the OS has one,
+ * only tells when to look again, so dropped notifications can't make the list drift */
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProcessEvent {
+    Started(ProcessInfo),
+    Exited(ProcessInfo),
+}
+
+impl ProcessEvent {
+    pub fn pid(&self) -> u32 {
+        match self {
+            ProcessEvent::Started(p) | ProcessEvent::Exited(p) => p.pid,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ProcessEvent::Started(p) | ProcessEvent::Exited(p) => &p.name,
+        }
+    }
+}
+
+/// Diffs consecutive snapshots by pid. A reused pid with a different name or start time counts as exit and start
+pub struct ProcessWatcher {
+    source: Arc<dyn ProcessSource>,
+    known: HashMap<u32, ProcessInfo>,
+}
+
+impl ProcessWatcher {
+    pub fn new(source: Arc<dyn ProcessSource>) -> Self {
+        Self {
+            source,
+            known: HashMap::new(),
+        }
+    }
+
+    /// Changes since the previous call, exits first, each group ordered by pid.
+    /// The first call reports every running process as started
+    pub fn poll(&mut self) -> Result<Vec<ProcessEvent>, TrackerError> {
+        let current: HashMap<u32, ProcessInfo> = self
+            .source
+            .running_procs()?
+            .into_iter()
+            .map(|p| (p.pid, p))
+            .collect();
+
+        let mut exited: Vec<ProcessInfo> = self
+            .known
+            .values()
+            .filter(|p| current.get(&p.pid).is_none_or(|c| !c.is_same(p)))
+            .cloned()
+            .collect();
+        let mut started: Vec<ProcessInfo> = current
+            .values()
+            .filter(|p| self.known.get(&p.pid).is_none_or(|k| !k.is_same(p)))
+            .cloned()
+            .collect();
+        exited.sort_by_key(|p| p.pid);
+        started.sort_by_key(|p| p.pid);
+
+        self.known = current;
+        Ok(exited
+            .into_iter()
+            .map(ProcessEvent::Exited)
+            .chain(started.into_iter().map(ProcessEvent::Started))
+            .collect())
+    }
+}
+
+/// Plain waiting for systems without process notifications
+pub struct ClockNotifier(pub Arc<dyn Clock>);
+
+impl ProcessNotifier for ClockNotifier {
+    fn wait(&self, timeout: Duration) -> bool {
+        self.0.sleep(timeout);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use crate::sys::clock::ManualClock;
+    use crate::sys::scripted::ScriptedProcessSource;
+
+    use super::*;
+
+    #[test]
+    fn reports_swaps_that_keep_process_count() {
+        let clock = Arc::new(ManualClock::new(SystemTime::UNIX_EPOCH));
+        let source = Arc::new(ScriptedProcessSource::new(clock.clone()));
+        source
+            .spawn_at(ProcessInfo::new("game", 1), 0, Some(10))
+            .spawn_at(ProcessInfo::new("editor", 2), 10, None)
+            .spawn_at(ProcessInfo::new("shell", 3), 0, Some(20))
+            .spawn_at(ProcessInfo::new("reused", 3), 20, None);
+        let mut watcher = ProcessWatcher::new(source);
+
+        let started = watcher.poll().unwrap();
+        assert_eq!(
+            started,
+            vec![
+                ProcessEvent::Started(ProcessInfo::new("game", 1)),
+                ProcessEvent::Started(ProcessInfo::new("shell", 3)),
+            ]
+        );
+        assert!(watcher.poll().unwrap().is_empty());
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(
+            watcher.poll().unwrap(),
+            vec![
+                ProcessEvent::Exited(ProcessInfo::new("game", 1)),
+                ProcessEvent::Started(ProcessInfo::new("editor", 2)),
+            ]
+        );
+
+        clock.advance(Duration::from_secs(10));
+        let events = watcher.poll().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].pid(), events[0].name()), (3, "shell"));
+        assert_eq!((events[1].pid(), events[1].name()), (3, "reused"));
+    }
+}
@@ -0,0 +1,44 @@
+use std::io;
+use std::mem;
+use std::time::Duration;
+
+use winapi::um::sysinfoapi::GetTickCount;
+use winapi::um::winuser::{
+    GetForegroundWindow, GetLastInputInfo, GetWindowThreadProcessId, LASTINPUTINFO,
+};
+
+use crate::error::TrackerError;
+use crate::sys::{FocusSource, IdleSource};
+
+/// Owner of the window returned by `GetForegroundWindow` and time since `GetLastInputInfo`.
+#[derive(Default)]
+pub struct WinInputSource;
+
+impl FocusSource for WinInputSource {
+    fn focused_pid(&self) -> Result<Option<u32>, TrackerError> {
+        let window = unsafe { GetForegroundWindow() };
+        if window.is_null() {
+            return Ok(None);
+        }
+        let mut pid: u32 = 0;
+        unsafe { GetWindowThreadProcessId(window, &mut pid) };
+        Ok(if pid == 0 { None } else { Some(pid) })
+    }
+}
+
+impl IdleSource for WinInputSource {
+    fn idle_time(&self) -> Result<Duration, TrackerError> {
+        let mut info = LASTINPUTINFO {
+            cbSize: mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return Err(TrackerError::Desktop(
+                io::Error::last_os_error().to_string(),
+            ));
+        }
+        // Both are milliseconds since boot and wrap around every 49 days
+        let idle = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+        Ok(Duration::from_millis(idle as u64))
+    }
+}
@@ -0,0 +1,198 @@
+pub mod input;
+pub mod process;
+pub mod user;
+
+use std::ffi::OsStr;
+use std::io::{Error, ErrorKind};
+use std::iter;
+use std::mem;
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+use std::result::Result;
+use std::sync::Mutex;
+use std::thread;
+use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, LRESULT, TRUE, UINT, WPARAM};
+use winapi::shared::ntdef::FALSE;
+use winapi::shared::windef::HWND;
+use winapi::um::consoleapi::SetConsoleCtrlHandler;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::wincon::{
+    GetConsoleWindow, CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT, CTRL_C_EVENT, CTRL_LOGOFF_EVENT,
+    CTRL_SHUTDOWN_EVENT,
+};
+use winapi::um::winuser::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW, ShowWindow,
+    TranslateMessage, MSG, SW_HIDE, WM_ENDSESSION, WM_QUERYENDSESSION, WNDCLASSW,
+};
+
+use super::{ProcessInfo, ProcessSource};
+use crate::error::TrackerError;
+use crate::sys::win_funcs::process::Process;
+
+/// Lists processes through winapi `EnumProcesses`. Command lines need Windows 8.1 or later
+#[derive(Default)]
+pub struct WinProcessSource;
+
+impl ProcessSource for WinProcessSource {
+    fn running_procs(&self) -> Result<Vec<ProcessInfo>, TrackerError> {
+        match enum_procs_by_name() {
+            Ok(procs) => Ok(procs
+                .into_iter()
+                .map(|p| {
+                    ProcessInfo::new(p.name(), p.pid())
+                        .with_exe(p.get_proc_path().ok())
+                        .with_cmdline(p.get_cmdline().unwrap_or_default())
+                        .with_start_time(p.get_start_time().ok())
+                })
+                .collect()),
+            Err(e) => Err(TrackerError::ProcessAccess(e.to_string())),
+        }
+    }
+}
+
+fn enum_procs() -> std::io::Result<Vec<u32>> {
+    let mut pids = Vec::<DWORD>::with_capacity(1024);
+    let mut size = 0;
+
+    if unsafe {
+        winapi::um::psapi::EnumProcesses(
+            pids.as_mut_ptr(),
+            (pids.capacity() * mem::size_of::<DWORD>()) as u32,
+            &mut size,
+        )
+    } as u8
+        == FALSE
+    {
+        return Err(Error::last_os_error());
+    }
+    let count = size as usize / mem::size_of::<DWORD>();
+    unsafe { pids.set_len(count) };
+    Ok(pids)
+}
+
+pub fn enum_procs_by_name() -> std::io::Result<Vec<Process>> {
+    let mut opened: u32 = 0;
+    let mut tried: u32 = 0;
+
+    let pids = enum_procs()?;
+    let mut processes = Vec::with_capacity(pids.capacity());
+
+    pids.into_iter()
+        .for_each(|pid| match Process::open_proc(pid) {
+            Ok(mut proc) => {
+                match proc.get_proc_name() {
+                    Ok(name) => {
+                        println!("Active process pid: {},named: {}", pid, name)
+                    }
+                    Err(e) => {
+                        eprintln!("Couldn't get process name with pid: {}.Reason: {}", pid, e)
+                    }
+                }
+                processes.push(proc);
+                opened += 1;
+            }
+            Err(e) => {
+                // println!("Failed to open process. Pid: {}.Reason: {}", pid, e);
+                tried += 1;
+            }
+        });
+    eprintln!("Enumerated. Opened successfully: {}/{}", opened, tried);
+    if opened == 0 {
+        return Err(Error::new(
+            ErrorKind::Other,
+            "App couldn't open any process. Try to launch the app with admin rights",
+        ));
+    }
+    return Ok(processes);
+}
+
+pub fn hide_console_window() {
+    let window = unsafe { GetConsoleWindow() };
+    if window != ptr::null_mut() {
+        unsafe {
+            ShowWindow(window, SW_HIDE);
+        }
+    }
+}
+
+/// Handler given to [`on_termination`], taken by whichever event comes first
+static TERMINATION: Mutex<Option<Box<dyn FnOnce() + Send>>> = Mutex::new(None);
+
+/// Runs `handler` once the console is closed or interrupted, or the user logs off or shuts down the system.
+/// Windows ends the process as soon as the handler returns
+pub fn on_termination(handler: impl FnOnce() + Send + 'static) {
+    *TERMINATION.lock().unwrap() = Some(Box::new(handler));
+    unsafe { SetConsoleCtrlHandler(Some(console_ctrl), TRUE) };
+    // Processes using user32 get logoff and shutdown only as window messages
+    thread::spawn(|| unsafe {
+        let class: Vec<u16> = OsStr::new("TrackerSession")
+            .encode_wide()
+            .chain(iter::once(0))
+            .collect();
+        let instance = GetModuleHandleW(ptr::null());
+        let mut window_class: WNDCLASSW = mem::zeroed();
+        window_class.lpfnWndProc = Some(session_window_proc);
+        window_class.hInstance = instance;
+        window_class.lpszClassName = class.as_ptr();
+        if RegisterClassW(&window_class) == 0 {
+            return eprintln!("Couldn't watch the session end: {}", Error::last_os_error());
+        }
+        // Never shown, but top-level windows are the ones told about the session end
+        let window = CreateWindowExW(
+            0,
+            class.as_ptr(),
+            class.as_ptr(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            instance,
+            ptr::null_mut(),
+        );
+        if window.is_null() {
+            return eprintln!("Couldn't watch the session end: {}", Error::last_os_error());
+        }
+        let mut msg: MSG = mem::zeroed();
+        while GetMessageW(&mut msg, ptr::null_mut(), 0, 0) > 0 {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    });
+}
+
+fn terminate() {
+    let handler = TERMINATION.lock().unwrap().take();
+    if let Some(handler) = handler {
+        handler();
+    }
+}
+
+unsafe extern "system" fn console_ctrl(event: DWORD) -> BOOL {
+    match event {
+        CTRL_C_EVENT | CTRL_BREAK_EVENT | CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT
+        | CTRL_SHUTDOWN_EVENT => {
+            terminate();
+            TRUE
+        }
+        _ => FALSE as BOOL,
+    }
+}
+
+unsafe extern "system" fn session_window_proc(
+    window: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_QUERYENDSESSION => TRUE as LRESULT,
+        WM_ENDSESSION if wparam != 0 => {
+            terminate();
+            0
+        }
+        _ => DefWindowProcW(window, msg, wparam, lparam),
+    }
+}
@@ -1,13 +1,16 @@
 use std::io::Error;
 use std::mem::MaybeUninit;
 
-use std::ptr::NonNull;
+use std::ptr::{self, NonNull};
+use std::slice;
 use std::time::{Duration, SystemTime};
 use std::{io::Result, mem};
 use winapi::ctypes::c_void;
-use winapi::shared::minwindef::{DWORD, FILETIME, HMODULE};
-use winapi::shared::ntdef::FALSE;
+use winapi::shared::minwindef::{DWORD, FILETIME, HMODULE, ULONG};
+use winapi::shared::ntdef::{FALSE, HANDLE, NTSTATUS, PVOID, UNICODE_STRING};
 use winapi::um::minwinbase::STILL_ACTIVE;
+use winapi::um::shellapi::CommandLineToArgvW;
+use winapi::um::winbase::LocalFree;
 use winapi::um::winnt::{self, MEMORY_BASIC_INFORMATION};
 
 const MASK: DWORD = winnt::PAGE_EXECUTE_READWRITE
@@ -21,6 +24,20 @@ const OPEN_PROCESS_RIGHTS: DWORD = winnt::PROCESS_QUERY_INFORMATION
     | winnt::PROCESS_VM_WRITE
     | winnt::PROCESS_VM_OPERATION;
 
+/// `PROCESSINFOCLASS` value giving the command line as a `UNICODE_STRING`, since Windows 8.1
+const PROCESS_COMMAND_LINE_INFORMATION: ULONG = 60;
+
+#[link(name = "ntdll")]
+extern "system" {
+    fn NtQueryInformationProcess(
+        ProcessHandle: HANDLE,
+        ProcessInformationClass: ULONG,
+        ProcessInformation: PVOID,
+        ProcessInformationLength: ULONG,
+        ReturnLength: *mut ULONG,
+    ) -> NTSTATUS;
+}
+
 #[derive(Clone, Debug)]
 pub struct Process {
     pid: u32,
@@ -84,7 +101,104 @@ impl Process {
         Ok(&self.name)
     }
 
+    /// Full path of the executable
+    pub fn get_proc_path(&self) -> Result<String> {
+        let mut buffer = vec![0u16; 1024];
+        let mut size = buffer.len() as u32;
+        if unsafe {
+            winapi::um::winbase::QueryFullProcessImageNameW(
+                self.handle.as_ptr(),
+                0,
+                buffer.as_mut_ptr(),
+                &mut size,
+            )
+        } == 0
+        {
+            return Err(Error::last_os_error());
+        }
+        Ok(String::from_utf16_lossy(&buffer[..size as usize]))
+    }
+
+    /// Arguments the process was started with, program first
+    pub fn get_cmdline(&self) -> Result<Vec<String>> {
+        let mut size: ULONG = 0;
+        // First call only tells the size
+        unsafe {
+            NtQueryInformationProcess(
+                self.handle.as_ptr(),
+                PROCESS_COMMAND_LINE_INFORMATION,
+                ptr::null_mut(),
+                0,
+                &mut size,
+            )
+        };
+        if size == 0 {
+            return Err(Error::other("Command line is not available"));
+        }
+        // u64 keeps the pointer inside UNICODE_STRING aligned
+        let mut buffer = vec![0u64; size as usize / mem::size_of::<u64>() + 1];
+        let status = unsafe {
+            NtQueryInformationProcess(
+                self.handle.as_ptr(),
+                PROCESS_COMMAND_LINE_INFORMATION,
+                buffer.as_mut_ptr().cast(),
+                size,
+                &mut size,
+            )
+        };
+        if status < 0 {
+            return Err(Error::other(format!(
+                "Couldn't read command line. Status: {:#x}",
+                status
+            )));
+        }
+        let mut line: Vec<u16> = unsafe {
+            let line = &*(buffer.as_ptr() as *const UNICODE_STRING);
+            if line.Buffer.is_null() {
+                return Ok(vec![]);
+            }
+            slice::from_raw_parts(line.Buffer, line.Length as usize / 2).to_vec()
+        };
+        // Empty line would be split into the path of this process instead
+        if line.is_empty() {
+            return Ok(vec![]);
+        }
+        line.push(0);
+
+        let mut argc = 0;
+        let argv = unsafe { CommandLineToArgvW(line.as_ptr(), &mut argc) };
+        if argv.is_null() {
+            return Err(Error::last_os_error());
+        }
+        let args = unsafe {
+            let args = (0..argc as usize)
+                .map(|i| {
+                    let arg = *argv.add(i);
+                    let len = (0..).take_while(|&c| *arg.add(c) != 0).count();
+                    String::from_utf16_lossy(slice::from_raw_parts(arg, len))
+                })
+                .collect();
+            LocalFree(argv.cast());
+            args
+        };
+        Ok(args)
+    }
+
+    /// Time since the process was created
     pub fn get_time(&self) -> Result<Duration> {
+        let process_creation_time = self.get_start_time()?;
+        let result = match SystemTime::now().duration_since(process_creation_time) {
+            Ok(n) => n,
+            Err(e) => {
+                println!("Couldn't get value: {}.Defaulting duration to 0", e);
+                Duration::new(0, 0)
+            }
+        };
+        Ok(result)
+    }
+
+    /// When the process was created
+    pub fn get_start_time(&self) -> Result<SystemTime> {
         let mut creation_time: FILETIME = create_def_filetime();
         let mut exit_time: FILETIME = create_def_filetime();
         let mut kernel_time: FILETIME = create_def_filetime();
@@ -100,18 +214,8 @@ impl Process {
             )
         } == 1
         {
-            let current_time = SystemTime::now();
-            let process_creation_time =
-                filetime_to_systemtime(&creation_time) - Duration::from_secs(11644473600); //windows EPOCH difference in secs
-
-            let result = match current_time.duration_since(process_creation_time) {
-                Ok(n) => n,
-                Err(e) => {
-                    println!("Couldn't get value: {}.Defaulting duration to 0", e);
-                    Duration::new(0, 0)
-                }
-            };
-            Ok(result)
+            Ok(filetime_to_systemtime(&creation_time) - Duration::from_secs(11644473600))
+        //windows EPOCH difference in secs
         } else {
             Err(Error::last_os_error())
         }
@@ -269,18 +373,3 @@ fn filetime_to_systemtime(filetime: &FILETIME) -> SystemTime {
 
     SystemTime::UNIX_EPOCH + duration
 }
-
-#[derive(Clone, Debug)]
-pub struct ProcessInfo {
-    pub name: String,
-    pub pid: u32,
-}
-
-impl ProcessInfo {
-    pub fn new(name: &str, pid: u32) -> Self {
-        Self {
-            name: name.to_string(),
-            pid,
-        }
-    }
-}
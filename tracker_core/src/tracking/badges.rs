@@ -1,91 +1,886 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, Days, Local, NaiveDate, TimeZone, Timelike, Weekday};
 use serde_derive::{Deserialize, Serialize};
 
+use crate::config::config_path;
+use crate::error::TrackerError;
+
+use super::usage::daily_usage_in;
+use super::{Session, TrackLog, TrackingMode};
+
+/* Badges are defined as data: the built-in ranks plus whatever the user adds in
+ * `badges.toml` (or `badges.json`) next to the config file.
+ * Ranks count a total, habits look at the days the app was used */
+
+const BADGE_FILES: [&str; 2] = ["badges.toml", "badges.json"];
+
+/// Red, green and blue
+pub type Rgb = (u8, u8, u8);
+
+lazy_static! {
+    /// Definitions and why the built-in ones are used instead, if the file couldn't be read
+    static ref BADGE_DEFS: (BadgeDefs, Option<String>) = BadgeDefs::load_default();
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Badge {
-    pub rank: BadgeRank,
+    /// Id of the definition. Named rank in files written before badges were configurable
+    #[serde(rename = "rank")]
+    pub id: String,
     pub username: String,
     pub description: String,
+    /// Unix seconds. Unknown for badges given before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub earned_at: Option<u64>,
+}
+
+/// What the threshold of a badge counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeMetric {
+    /// Whole hours of the tracking mode
+    #[default]
+    Hours,
+    /// Sessions of the app
+    Sessions,
 }
 
-pub fn get_badge(elapsed_secs: u64, username: &str) -> Option<Badge> {
-    let mut rank: Option<BadgeRank> = None;
-    let mut description = String::new();
-    let elapsed_hours: u64 = elapsed_secs / 3600;
-    match elapsed_hours {
-        0 => {
-            rank = Some(BadgeRank::Initial);
-            description.push_str("App's just been added.");
+impl BadgeMetric {
+    pub fn value(&self, log: &TrackLog, mode: TrackingMode) -> u64 {
+        match self {
+            BadgeMetric::Hours => log.time(mode) / 3600,
+            BadgeMetric::Sessions => log.sessions.len() as u64,
         }
-        1 => {
-            rank = Some(BadgeRank::Common);
-            description.push_str("You've been using app for an hour. Keep it up.");
+    }
+
+    /// End of the session that took the app to `threshold`.
+    /// Unknown if legacy totals alone got there, or nothing did
+    pub fn reached_at(&self, log: &TrackLog, mode: TrackingMode, threshold: u64) -> Option<u64> {
+        let mut sessions: Vec<&Session> = log.sessions.iter().collect();
+        // Legacy totals were there before anything with dates
+        sessions.sort_by_key(|s| (!s.legacy, s.end));
+        let mut total = 0;
+        for session in sessions {
+            let value = match self {
+                BadgeMetric::Hours => {
+                    total += session.time(mode);
+                    total / 3600
+                }
+                BadgeMetric::Sessions => {
+                    total += 1;
+                    total
+                }
+            };
+            if value >= threshold {
+                return (!session.legacy).then_some(session.end);
+            }
         }
-        2 => {
-            rank = Some(BadgeRank::Rare);
-            description.push_str("You've been using app for two hours. Not bad.");
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BadgeDef {
+    pub id: String,
+    #[serde(default)]
+    pub metric: BadgeMetric,
+    pub threshold: u64,
+    pub icon: String,
+    /// `#rrggbb`
+    pub color: String,
+    pub description: String,
+}
+
+impl BadgeDef {
+    fn new(threshold: u64, id: &str, icon: &str, color: &str, description: &str) -> Self {
+        Self {
+            id: id.to_owned(),
+            metric: BadgeMetric::Hours,
+            threshold,
+            icon: icon.to_owned(),
+            color: color.to_owned(),
+            description: description.to_owned(),
         }
-        10 => {
-            rank = Some(BadgeRank::Experienced);
-            description
-                .push_str("You've been using app for ten hours. I think you're already into it.");
+    }
+
+    pub fn badge(&self, username: &str, earned_at: u64) -> Badge {
+        Badge {
+            id: self.id.to_owned(),
+            username: username.to_owned(),
+            description: self.description.to_owned(),
+            earned_at: Some(earned_at),
         }
-        50 => {
-            rank = Some(BadgeRank::Advanced);
-            description.push_str("You've been using app for an fifty hours. Point of no return.");
+    }
+
+    pub fn rgb(&self) -> Option<Rgb> {
+        parse_color(&self.color)
+    }
+}
+
+/// What an achievement asks for. Days follow the local timezone
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Habit {
+    /// Used on this many days in a row
+    Streak { days: u64 },
+    /// Used, but less than `max_minutes`, on each of this many days in a row
+    LightUse { max_minutes: u64, days: u64 },
+    /// A session started before this hour of the day
+    EarlyStart { before_hour: u32 },
+    /// Not used on a whole weekend since the first use
+    WeekendOff,
+}
+
+impl Habit {
+    fn target(&self) -> u64 {
+        match *self {
+            Habit::Streak { days } | Habit::LightUse { days, .. } => days,
+            Habit::EarlyStart { .. } => 1,
+            Habit::WeekendOff => 2,
         }
-        100 => {
-            rank = Some(BadgeRank::Pro);
-            description
-                .push_str("You've been using app for one hundred hours. You're already hooked.");
+    }
+
+    /// `days` are seconds of use on every day the app was used
+    fn progress<Tz: TimeZone>(
+        &self,
+        log: &TrackLog,
+        days: &BTreeMap<NaiveDate, u64>,
+        today: NaiveDate,
+        tz: &Tz,
+    ) -> Progress {
+        let target = self.target();
+        let Some(first) = days.keys().next().copied() else {
+            return Progress::new(0, target);
+        };
+        let yesterday = today.pred_opt().unwrap_or(today);
+
+        let current = match *self {
+            Habit::Streak { .. } => {
+                let used = |day: NaiveDate| days.contains_key(&day);
+                let (longest, last) = day_runs(first, today, used);
+                if longest >= target {
+                    target
+                } else if last > 0 {
+                    last
+                } else {
+                    // Today isn't over, the streak may go on
+                    day_runs(first, yesterday, used).1
+                }
+            }
+            Habit::LightUse { max_minutes, .. } => {
+                let light = |day: NaiveDate| days.get(&day).is_some_and(|s| *s < max_minutes * 60);
+                // Only whole days count
+                let (longest, last) = day_runs(first, yesterday, light);
+                if longest >= target {
+                    target
+                } else if light(today) || !days.contains_key(&today) {
+                    // Today isn't over, the run may go on
+                    last
+                } else {
+                    0
+                }
+            }
+            Habit::EarlyStart { before_hour } => log
+                .sessions
+                .iter()
+                .filter(|s| !s.legacy && s.duration > 0)
+                .any(|s| {
+                    tz.timestamp_opt(s.start as i64, 0)
+                        .earliest()
+                        .is_some_and(|t| t.hour() < before_hour)
+                }) as u64,
+            Habit::WeekendOff => {
+                let days_to_saturday = (Weekday::Sat.num_days_from_monday() + 7
+                    - first.weekday().num_days_from_monday())
+                    % 7;
+                let mut saturday = first + Days::new(days_to_saturday as u64);
+                let mut current = 0;
+                while saturday <= today {
+                    let sunday = saturday + Days::new(1);
+                    let off = [saturday, sunday]
+                        .iter()
+                        .filter(|day| **day < today && !days.contains_key(day))
+                        .count() as u64;
+                    if off == 2 {
+                        current = 2;
+                        break;
+                    }
+                    // A weekend that is over and was used starts nothing
+                    current = if sunday >= today { off } else { 0 };
+                    saturday = saturday + Days::new(7);
+                }
+                current
+            }
+        };
+        Progress::new(current, target)
+    }
+}
+
+/// Achievement for a habit, evaluated against the session history
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HabitDef {
+    pub id: String,
+    #[serde(flatten)]
+    pub habit: Habit,
+    pub icon: String,
+    /// `#rrggbb`
+    pub color: String,
+    pub description: String,
+}
+
+impl HabitDef {
+    fn new(habit: Habit, id: &str, icon: &str, color: &str, description: &str) -> Self {
+        Self {
+            id: id.to_owned(),
+            habit,
+            icon: icon.to_owned(),
+            color: color.to_owned(),
+            description: description.to_owned(),
         }
-        500 => {
-            rank = Some(BadgeRank::Insane);
-            description.push_str(
-                "You've been using app for five hundred hours. You really like this, don't you?",
-            );
+    }
+
+    pub fn badge(&self, username: &str, earned_at: u64) -> Badge {
+        Badge {
+            id: self.id.to_owned(),
+            username: username.to_owned(),
+            description: self.description.to_owned(),
+            earned_at: Some(earned_at),
         }
-        1000 => {
-            rank = Some(BadgeRank::Lunatic);
-            description.push_str(
-                "You've been using app for one thousand hours. You know everything about this app.",
-            );
+    }
+
+    pub fn rgb(&self) -> Option<Rgb> {
+        parse_color(&self.color)
+    }
+}
+
+/// How far an app got towards an achievement, e.g. 4 of 7 days
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Progress {
+    pub current: u64,
+    pub target: u64,
+}
+
+impl Progress {
+    pub fn new(current: u64, target: u64) -> Self {
+        Self {
+            current: current.min(target),
+            target,
         }
-        3000 => {
-            rank = Some(BadgeRank::TouchGrass);
-            description.push_str(
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.current >= self.target
+    }
+
+    /// Between 0 and 1
+    pub fn fraction(&self) -> f32 {
+        if self.target == 0 {
+            return 1.0;
+        }
+        self.current as f32 / self.target as f32
+    }
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.current, self.target)
+    }
+}
+
+/// Contents of a badges file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BadgeFile {
+    /// Only the badges and habits of the file are given
+    replace_builtin: bool,
+    badges: Vec<BadgeDef>,
+    habits: Vec<HabitDef>,
+}
+
+/// Every badge and habit that can be earned, in the order they're checked and shown
+#[derive(Debug, Clone, PartialEq)]
+pub struct BadgeDefs {
+    defs: Vec<BadgeDef>,
+    habits: Vec<HabitDef>,
+}
+
+impl Default for BadgeDefs {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl BadgeDefs {
+    /// Ranks for total hours with an app
+    pub fn builtin() -> Self {
+        let defs = vec![
+            BadgeDef::new(0, "Initial", "🔓", "#a5a5a0", "App's just been added."),
+            BadgeDef::new(
+                1,
+                "Common",
+                "🕑",
+                "#00b200",
+                "You've been using app for an hour. Keep it up.",
+            ),
+            BadgeDef::new(
+                2,
+                "Rare",
+                "⏳",
+                "#426cff",
+                "You've been using app for two hours. Not bad.",
+            ),
+            BadgeDef::new(
+                10,
+                "Experienced",
+                "🔥",
+                "#fd8251",
+                "You've been using app for ten hours. I think you're already into it.",
+            ),
+            BadgeDef::new(
+                50,
+                "Advanced",
+                "🌀",
+                "#fe0000",
+                "You've been using app for an fifty hours. Point of no return.",
+            ),
+            BadgeDef::new(
+                100,
+                "Pro",
+                "🕞",
+                "#9f3fbf",
+                "You've been using app for one hundred hours. You're already hooked.",
+            ),
+            BadgeDef::new(
+                500,
+                "Insane",
+                "⏰",
+                "#33b3a6",
+                "You've been using app for five hundred hours. You really like this, don't you?",
+            ),
+            BadgeDef::new(
+                1000,
+                "Lunatic",
+                "🎴",
+                "#980000",
+                "You've been using app for one thousand hours. You know everything about this app.",
+            ),
+            BadgeDef::new(
+                3000,
+                "TouchGrass",
+                "🎉",
+                "#b15433",
                 "You've been using app for three thousand hours. Can't believe I just said that.",
-            );
+            ),
+            BadgeDef::new(
+                10000,
+                "Master",
+                "💎",
+                "#5f2572",
+                "You've been using app for ten thousand hours. You've mastered it all",
+            ),
+        ];
+        let habits = vec![
+            HabitDef::new(
+                Habit::Streak { days: 7 },
+                "Streak",
+                "📆",
+                "#f5b041",
+                "You've used app seven days in a row.",
+            ),
+            HabitDef::new(
+                Habit::LightUse {
+                    max_minutes: 60,
+                    days: 7,
+                },
+                "LightWeek",
+                "🌱",
+                "#52be80",
+                "Less than an hour a day for a whole week. Well balanced.",
+            ),
+            HabitDef::new(
+                Habit::EarlyStart { before_hour: 8 },
+                "EarlyBird",
+                "🌅",
+                "#f1948a",
+                "You've started app before 8am.",
+            ),
+            HabitDef::new(
+                Habit::WeekendOff,
+                "WeekendOff",
+                "🏖",
+                "#5dade2",
+                "You've taken a whole weekend off app.",
+            ),
+        ];
+        Self { defs, habits }
+    }
+
+    /// Built-in badges with the ones of the file. A badge with the id of a built-in one replaces it
+    pub fn load(path: &Path) -> Result<Self, TrackerError> {
+        let data = fs::read_to_string(path)?;
+        let file: BadgeFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&data)?,
+            _ => toml::from_str(&data)?,
+        };
+
+        let mut defs = if file.replace_builtin {
+            Self {
+                defs: vec![],
+                habits: vec![],
+            }
+        } else {
+            Self::builtin()
+        };
+        for def in file.badges {
+            check_def(&def.id, &def.color)?;
+            match defs.defs.iter_mut().find(|d| d.id == def.id) {
+                Some(same) => *same = def,
+                None => defs.defs.push(def),
+            }
         }
-        10000 => {
-            rank = Some(BadgeRank::Master);
-            description
-                .push_str("You've been using app for ten thousand hours. You've mastered it all");
+        for habit in file.habits {
+            check_def(&habit.id, &habit.color)?;
+            if defs.get(&habit.id).is_some() {
+                return Err(TrackerError::Parse(format!(
+                    "Habit {}: there is a badge with the same id",
+                    habit.id
+                )));
+            }
+            match defs.habits.iter_mut().find(|h| h.id == habit.id) {
+                Some(same) => *same = habit,
+                None => defs.habits.push(habit),
+            }
         }
+        Ok(defs)
+    }
 
-        _ => (),
+    fn load_default() -> (Self, Option<String>) {
+        let Some(path) = badges_path() else {
+            return (Self::builtin(), None);
+        };
+        match Self::load(&path) {
+            Ok(defs) => (defs, None),
+            Err(e) => {
+                let msg = format!("Couldn't read badges {}: {}", path.display(), e);
+                eprintln!("{}", msg);
+                (Self::builtin(), Some(msg))
+            }
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&BadgeDef> {
+        self.defs.iter().find(|d| d.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &BadgeDef> {
+        self.defs.iter()
+    }
+
+    pub fn habits(&self) -> impl Iterator<Item = &HabitDef> {
+        self.habits.iter()
+    }
+
+    /// Icon and color of a badge or habit
+    pub fn look(&self, id: &str) -> Option<(&str, Option<Rgb>)> {
+        match self.get(id) {
+            Some(def) => Some((&def.icon, def.rgb())),
+            None => self
+                .habits
+                .iter()
+                .find(|h| h.id == id)
+                .map(|h| (h.icon.as_str(), h.rgb())),
+        }
+    }
+
+    /// Progress of the app towards every habit in the local timezone
+    pub fn habit_progress(
+        &self,
+        log: &TrackLog,
+        mode: TrackingMode,
+        now: u64,
+    ) -> Vec<(&HabitDef, Progress)> {
+        self.habit_progress_in(log, mode, now, &Local)
+    }
+
+    pub fn habit_progress_in<Tz: TimeZone>(
+        &self,
+        log: &TrackLog,
+        mode: TrackingMode,
+        now: u64,
+        tz: &Tz,
+    ) -> Vec<(&HabitDef, Progress)> {
+        habits_progress(self.habits.iter(), log, mode, now, tz)
+    }
+
+    /// Badges and habits the app has reached but doesn't have yet. Ranks are earned when
+    /// the session that reached them ended, habits at `now`.
+    /// Every rank up to the current value is given, so imported or backfilled time skips none
+    pub fn new_badges(
+        &self,
+        log: &TrackLog,
+        mode: TrackingMode,
+        username: &str,
+        now: u64,
+    ) -> Vec<Badge> {
+        self.new_badges_in(log, mode, username, now, &Local)
+    }
+
+    pub fn new_badges_in<Tz: TimeZone>(
+        &self,
+        log: &TrackLog,
+        mode: TrackingMode,
+        username: &str,
+        now: u64,
+        tz: &Tz,
+    ) -> Vec<Badge> {
+        let is_new = |id: &str| !log.badges.iter().any(|b| b.id == id);
+        let ranks = self
+            .defs
+            .iter()
+            .filter(|d| is_new(&d.id) && d.metric.value(log, mode) >= d.threshold)
+            .map(|d| Badge {
+                earned_at: d.metric.reached_at(log, mode, d.threshold).or(Some(now)),
+                ..d.badge(username, now)
+            });
+        // Habits already earned aren't evaluated again
+        let pending = self.habits.iter().filter(|h| is_new(&h.id));
+        let habits = habits_progress(pending, log, mode, now, tz)
+            .into_iter()
+            .filter(|(_, progress)| progress.is_complete())
+            .map(|(h, _)| h.badge(username, now));
+        ranks.chain(habits).collect()
+    }
+
+    /// Same as [`BadgeDefs::new_badges`] for badges reached while nothing was checking,
+    /// e.g. with older versions or on another machine. When habits were completed,
+    /// or ranks were reached without dated sessions, is unknown
+    pub fn missed_badges(
+        &self,
+        log: &TrackLog,
+        mode: TrackingMode,
+        username: &str,
+        now: u64,
+    ) -> Vec<Badge> {
+        let mut badges = self.new_badges(log, mode, username, now);
+        for badge in badges.iter_mut() {
+            badge.earned_at = self
+                .get(&badge.id)
+                .and_then(|d| d.metric.reached_at(log, mode, d.threshold));
+        }
+        badges
+    }
+}
+
+fn habits_progress<'a, Tz: TimeZone>(
+    habits: impl Iterator<Item = &'a HabitDef>,
+    log: &TrackLog,
+    mode: TrackingMode,
+    now: u64,
+    tz: &Tz,
+) -> Vec<(&'a HabitDef, Progress)> {
+    let Some(today) = tz
+        .timestamp_opt(now as i64, 0)
+        .earliest()
+        .map(|t| t.date_naive())
+    else {
+        return vec![];
     };
+    let days = daily_usage_in(log, mode, tz);
+    habits
+        .map(|h| (h, h.habit.progress(log, &days, today, tz)))
+        .collect()
+}
 
-    if rank.is_some() {
-        return Some(Badge {
-            rank: rank.unwrap(),
-            username: username.to_owned(),
-            description,
-        });
-    } else {
+/// Longest run of days in `from..=to` that match, and the run ending at `to`
+fn day_runs(from: NaiveDate, to: NaiveDate, matches: impl Fn(NaiveDate) -> bool) -> (u64, u64) {
+    let (mut longest, mut run) = (0, 0);
+    let mut day = from;
+    while day <= to {
+        run = if matches(day) { run + 1 } else { 0 };
+        longest = longest.max(run);
+        let Some(next) = day.succ_opt() else {
+            break;
+        };
+        day = next;
+    }
+    (longest, run)
+}
+
+pub(crate) fn parse_color(color: &str) -> Option<Rgb> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
         return None;
     }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+fn check_def(id: &str, color: &str) -> Result<(), TrackerError> {
+    if id.is_empty() {
+        return Err(TrackerError::Parse("Badge without an id".to_owned()));
+    }
+    if parse_color(color).is_none() {
+        return Err(TrackerError::Parse(format!(
+            "Badge {}: color should look like #rrggbb, got {}",
+            id, color
+        )));
+    }
+    Ok(())
+}
+
+/// Badges as they were at startup
+pub fn use_badge_defs() -> &'static BadgeDefs {
+    &BADGE_DEFS.0
+}
+
+/// Why the badges file was ignored, if it was
+pub fn badge_defs_error() -> Option<&'static str> {
+    BADGE_DEFS.1.as_deref()
+}
+
+/// First of `badges.toml` and `badges.json` found in the config directory
+pub fn badges_path() -> Option<PathBuf> {
+    let dir = config_path()?.parent()?.to_path_buf();
+    BADGE_FILES
+        .iter()
+        .map(|file| dir.join(file))
+        .find(|path| path.exists())
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
-pub enum BadgeRank {
-    Initial,
-    Common,
-    Rare,
-    Experienced,
-    Advanced,
-    Pro,
-    Insane,
-    Lunatic,
-    TouchGrass,
-    Master,
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::tracking::Session;
+
+    use super::*;
+
+    /// 2023-03-01 is a Wednesday
+    fn at(day: u32, hour: u32) -> u64 {
+        NaiveDate::from_ymd_opt(2023, 3, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp() as u64
+    }
+
+    fn log_with(sessions: &[(u32, u32, u64)]) -> TrackLog {
+        let mut log = TrackLog::new("alice", "game", "Game");
+        for (day, hour, minutes) in sessions {
+            let start = at(*day, *hour);
+            let mut session = Session::new(start);
+            session.extend(start + minutes * 60, minutes * 60);
+            log.sessions.push(session);
+        }
+        log
+    }
+
+    fn utc() -> chrono::FixedOffset {
+        chrono::FixedOffset::east_opt(0).unwrap()
+    }
+
+    #[test]
+    fn custom_badges_extend_builtin_ones() {
+        let path = env::temp_dir().join(format!("tracker_badges_{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            r##"
+[[badges]]
+id = "Regular"
+metric = "sessions"
+threshold = 2
+icon = "📅"
+color = "#00ff80"
+description = "Opened the app twice."
+
+[[badges]]
+id = "Common"
+threshold = 1
+icon = "⭐"
+color = "#ffffff"
+description = "An hour in."
+
+[[habits]]
+id = "Month"
+kind = "streak"
+days = 30
+icon = "🗓"
+color = "#123456"
+description = "A month in a row."
+"##,
+        )
+        .unwrap();
+        let defs = BadgeDefs::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(defs.iter().count(), BadgeDefs::builtin().iter().count() + 1);
+        assert_eq!(defs.get("Common").unwrap().icon, "⭐");
+        assert_eq!(defs.get("Regular").unwrap().rgb(), Some((0, 255, 128)));
+        let month = defs.habits().find(|h| h.id == "Month").unwrap();
+        assert_eq!(month.habit, Habit::Streak { days: 30 });
+        assert_eq!(defs.look("Month"), Some(("🗓", Some((0x12, 0x34, 0x56)))));
+
+        let log = log_with(&[(1, 12, 60), (2, 12, 60)]);
+        let ids =
+            |badges: Vec<Badge>| -> Vec<String> { badges.into_iter().map(|b| b.id).collect() };
+        assert_eq!(
+            ids(defs.new_badges_in(&log, TrackingMode::Uptime, "alice", at(2, 14), &utc())),
+            vec!["Initial", "Common", "Rare", "Regular"]
+        );
+    }
+
+    #[test]
+    fn gives_every_passed_rank_once() {
+        let defs = BadgeDefs::builtin();
+        // Starts past several thresholds at once, as an import or backfill does
+        let mut log = log_with(&[(1, 12, 11 * 60 + 1)]);
+        log.badges
+            .push(defs.get("Common").unwrap().badge("alice", 100));
+
+        let now = at(2, 12);
+        let new = defs.new_badges_in(&log, TrackingMode::Uptime, "alice", now, &utc());
+        let ids: Vec<&str> = new.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(ids, vec!["Initial", "Rare", "Experienced"]);
+        // All of them with the session that got there
+        let end = log.sessions[0].end;
+        assert!(new.iter().all(|b| b.earned_at == Some(end)));
+
+        log.badges.extend(new);
+        assert!(defs
+            .new_badges_in(&log, TrackingMode::Uptime, "alice", now + 60, &utc())
+            .is_empty());
+    }
+
+    #[test]
+    fn backfilled_ranks_keep_when_they_were_reached() {
+        let defs = BadgeDefs::builtin();
+        let mut log = log_with(&[(1, 12, 60), (2, 12, 60)]);
+        log.sessions.push(Session::legacy(3600 + 1800));
+
+        let earned: Vec<(String, Option<u64>)> = defs
+            .missed_badges(&log, TrackingMode::Uptime, "alice", at(3, 12))
+            .into_iter()
+            .map(|b| (b.id, b.earned_at))
+            .collect();
+        // Legacy totals came first, so the lower ranks have no date
+        assert_eq!(
+            earned,
+            vec![
+                ("Initial".to_owned(), None),
+                ("Common".to_owned(), None),
+                ("Rare".to_owned(), Some(log.sessions[0].end)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_days_are_not_light() {
+        // A few minutes on the first day, then left alone for a week
+        let log = log_with(&[(1, 9, 10)]);
+        let light_week = |now: u64| {
+            progress(&log, now)
+                .into_iter()
+                .find(|(id, _)| id == "LightWeek")
+                .unwrap()
+                .1
+        };
+        assert_eq!(light_week(at(2, 12)), "1/7");
+        assert_eq!(light_week(at(9, 12)), "0/7");
+
+        let new = BadgeDefs::builtin().new_badges_in(
+            &log,
+            TrackingMode::Uptime,
+            "alice",
+            at(9, 12),
+            &utc(),
+        );
+        assert!(new.iter().all(|b| b.id != "LightWeek"));
+    }
+
+    #[test]
+    fn ranks_earned_live_always_have_a_date() {
+        let defs = BadgeDefs::builtin();
+        let log = TrackLog::new("alice", "game", "Game");
+        let now = at(1, 12);
+
+        let live = defs.new_badges_in(&log, TrackingMode::Uptime, "alice", now, &utc());
+        assert_eq!(live[0].id, "Initial");
+        assert_eq!(live[0].earned_at, Some(now));
+
+        // Only a backfill can't tell when
+        let missed = defs.missed_badges(&log, TrackingMode::Uptime, "alice", now);
+        assert_eq!(missed[0].id, "Initial");
+        assert_eq!(missed[0].earned_at, None);
+    }
+
+    fn progress(log: &TrackLog, now: u64) -> Vec<(String, String)> {
+        BadgeDefs::builtin()
+            .habit_progress_in(log, TrackingMode::Uptime, now, &utc())
+            .into_iter()
+            .map(|(h, p)| (h.id.to_owned(), p.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn habits_show_progress() {
+        // Half an hour Wednesday to Saturday, nothing yet on Sunday morning
+        let log = log_with(&[(1, 9, 30), (2, 9, 30), (3, 9, 30), (4, 9, 30)]);
+        let pairs = |list: &[(&str, &str)]| -> Vec<(String, String)> {
+            list.iter()
+                .map(|(id, p)| (id.to_string(), p.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            progress(&log, at(5, 6)),
+            pairs(&[
+                ("Streak", "4/7"),
+                ("LightWeek", "4/7"),
+                ("EarlyBird", "0/1"),
+                ("WeekendOff", "0/2"),
+            ])
+        );
+
+        // Early start, a weekend off, then two hours on Monday
+        let log = log_with(&[(1, 7, 30), (2, 9, 30), (3, 9, 30), (6, 10, 120)]);
+        assert_eq!(
+            progress(&log, at(6, 13)),
+            pairs(&[
+                ("Streak", "1/7"),
+                ("LightWeek", "0/7"),
+                ("EarlyBird", "1/1"),
+                ("WeekendOff", "2/2"),
+            ])
+        );
+
+        let defs = BadgeDefs::builtin();
+        let mut log = log;
+        let new = defs.new_badges_in(&log, TrackingMode::Uptime, "alice", at(6, 13), &utc());
+        let ids: Vec<&str> = new.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["Initial", "Common", "Rare", "EarlyBird", "WeekendOff"]
+        );
+        log.badges.extend(new);
+        assert!(defs
+            .new_badges_in(&log, TrackingMode::Uptime, "alice", at(7, 13), &utc())
+            .is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_color() {
+        let path = env::temp_dir().join(format!("tracker_badges_{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"badges": [{"id": "x", "threshold": 1, "icon": "x", "color": "red", "description": ""}]}"#,
+        )
+        .unwrap();
+        let err = BadgeDefs::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(err.to_string().contains("#rrggbb"));
+    }
 }
@@ -0,0 +1,334 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::Write;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use chrono::{Days, Local, NaiveDate, TimeZone};
+use serde_derive::Serialize;
+
+use crate::config::use_config;
+use crate::error::TrackerError;
+
+use super::usage::{day_start, split_by_day};
+use super::{TrackLog, TrackingMode};
+
+/* Usage history as flat rows for spreadsheets and notebooks */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// What a single row stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportRows {
+    Sessions,
+    /// Time of an app within a calendar day
+    Days,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub rows: ExportRows,
+    /// First day, inclusive
+    pub from: NaiveDate,
+    /// Last day, inclusive
+    pub to: NaiveDate,
+    pub mode: TrackingMode,
+}
+
+impl ExportOptions {
+    /// Whole history
+    pub fn new(format: ExportFormat, rows: ExportRows, mode: TrackingMode) -> Self {
+        Self {
+            format,
+            rows,
+            from: NaiveDate::MIN,
+            to: NaiveDate::MAX,
+            mode,
+        }
+    }
+
+    pub fn between(mut self, from: NaiveDate, to: NaiveDate) -> Self {
+        self.from = from;
+        self.to = to;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportRow {
+    pub app: String,
+    pub display_name: String,
+    pub user: String,
+    /// RFC 3339 time for sessions, date for days
+    pub start: String,
+    /// Exclusive. Following date for days
+    pub end: String,
+    pub seconds: u64,
+    /// Ids of badges of the app earned between start and end
+    pub badges: Vec<String>,
+}
+
+const CSV_HEADER: &str = "app,display_name,user,start,end,seconds,badges";
+
+/// Writes rows of every app touching the range in the local timezone. Returns the number of rows
+pub fn export<W: Write>(
+    logs: &[TrackLog],
+    options: &ExportOptions,
+    out: W,
+) -> Result<usize, TrackerError> {
+    let rows = export_rows_in(logs, options, &Local);
+    write_rows(&rows, options.format, out)?;
+    Ok(rows.len())
+}
+
+/// Rows in the given timezone. Sessions that cross the range are kept whole,
+/// legacy sessions have no dates and are skipped
+pub fn export_rows_in<Tz: TimeZone>(
+    logs: &[TrackLog],
+    options: &ExportOptions,
+    tz: &Tz,
+) -> Vec<ExportRow>
+where
+    Tz::Offset: Display,
+{
+    let in_range = |day: &NaiveDate| options.from <= *day && *day <= options.to;
+    let mut rows = vec![];
+
+    for log in logs {
+        // Badges earned at an unknown time don't belong to any row
+        let row = |start: String, end: String, seconds: u64, earned: Range<u64>| ExportRow {
+            app: log.process_name.to_owned(),
+            display_name: log.display_name.to_owned(),
+            user: log.username.to_owned(),
+            start,
+            end,
+            seconds,
+            badges: log
+                .badges
+                .iter()
+                .filter(|b| b.earned_at.is_some_and(|at| earned.contains(&at)))
+                .map(|b| b.id.to_owned())
+                .collect(),
+        };
+
+        match options.rows {
+            ExportRows::Sessions => {
+                let mut sessions: Vec<_> = log
+                    .sessions
+                    .iter()
+                    .filter(|s| {
+                        split_by_day(s, options.mode, tz)
+                            .iter()
+                            .any(|(day, _)| in_range(day))
+                    })
+                    .collect();
+                sessions.sort_by_key(|s| s.start);
+                for s in sessions {
+                    rows.push(row(
+                        rfc3339(s.start, tz),
+                        rfc3339(s.end, tz),
+                        s.time(options.mode),
+                        // Badges are checked while the session goes on, the last time at its end
+                        s.start..s.end + 1,
+                    ));
+                }
+            }
+            ExportRows::Days => {
+                let mut days: BTreeMap<NaiveDate, u64> = BTreeMap::new();
+                for s in &log.sessions {
+                    for (day, seconds) in split_by_day(s, options.mode, tz) {
+                        if in_range(&day) {
+                            *days.entry(day).or_default() += seconds;
+                        }
+                    }
+                }
+                for (day, seconds) in days.into_iter().filter(|(_, s)| *s > 0) {
+                    let next = day + Days::new(1);
+                    rows.push(row(
+                        day.to_string(),
+                        next.to_string(),
+                        seconds,
+                        day_start(day, tz) as u64..day_start(next, tz) as u64,
+                    ));
+                }
+            }
+        }
+    }
+    rows
+}
+
+pub fn write_rows<W: Write>(
+    rows: &[ExportRow],
+    format: ExportFormat,
+    mut out: W,
+) -> Result<(), TrackerError> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(out, "{}", CSV_HEADER)?;
+            for r in rows {
+                let fields = [
+                    csv_field(&r.app),
+                    csv_field(&r.display_name),
+                    csv_field(&r.user),
+                    csv_field(&r.start),
+                    csv_field(&r.end),
+                    r.seconds.to_string(),
+                    csv_field(&r.badges.join(";")),
+                ];
+                writeln!(out, "{}", fields.join(","))?;
+            }
+        }
+        ExportFormat::Ndjson => {
+            for r in rows {
+                writeln!(out, "{}", serde_json::to_string(r)?)?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// File in the downloads directory named after today's date
+pub fn default_export_path(format: ExportFormat) -> PathBuf {
+    let dir = dirs::download_dir()
+        .or_else(dirs::document_dir)
+        .unwrap_or_else(|| use_config().data_dir());
+    dir.join(format!(
+        "tracker-usage-{}.{}",
+        Local::now().format("%Y-%m-%d"),
+        format.extension()
+    ))
+}
+
+fn rfc3339<Tz: TimeZone>(unix_secs: u64, tz: &Tz) -> String
+where
+    Tz::Offset: Display,
+{
+    tz.timestamp_opt(unix_secs as i64, 0)
+        .earliest()
+        .map_or_else(String::new, |t| t.to_rfc3339())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use crate::tracking::badges::BadgeDefs;
+    use crate::tracking::Session;
+
+    use super::*;
+
+    fn date(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 3, d).unwrap()
+    }
+
+    fn log() -> TrackLog {
+        let tz = FixedOffset::east_opt(0).unwrap();
+        let at = |d: u32, hour: u32| -> u64 {
+            tz.from_local_datetime(&date(d).and_hms_opt(hour, 0, 0).unwrap())
+                .unwrap()
+                .timestamp() as u64
+        };
+        let mut log = TrackLog::new("alice", "game", "Game, \"deluxe\"");
+        log.badges.push(
+            BadgeDefs::builtin()
+                .get("Initial")
+                .unwrap()
+                .badge("alice", at(1, 23)),
+        );
+        // 22:00 to 02:00 over midnight, then a session two days later
+        for (start, end) in [(at(1, 22), at(2, 2)), (at(4, 10), at(4, 11))] {
+            let mut session = Session::new(start);
+            session.extend(end, end - start);
+            log.sessions.push(session);
+        }
+        log.sessions.push(Session::legacy(1000));
+        log
+    }
+
+    #[test]
+    fn exports_sessions_and_days_in_range() {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let options = ExportOptions::new(
+            ExportFormat::Csv,
+            ExportRows::Sessions,
+            TrackingMode::Uptime,
+        )
+        .between(date(2), date(3));
+
+        let sessions = export_rows_in(&[log()], &options, &utc);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].start, "2023-03-01T22:00:00+00:00");
+        assert_eq!(sessions[0].seconds, 4 * 3600);
+        assert_eq!(sessions[0].badges, vec!["Initial"]);
+
+        let days = export_rows_in(
+            &[log()],
+            &ExportOptions {
+                rows: ExportRows::Days,
+                ..options
+            },
+            &utc,
+        );
+        assert_eq!(days.len(), 1);
+        assert_eq!(
+            (
+                days[0].start.as_str(),
+                days[0].end.as_str(),
+                days[0].seconds
+            ),
+            ("2023-03-02", "2023-03-03", 2 * 3600)
+        );
+        // Badge was earned the day before
+        assert!(days[0].badges.is_empty());
+    }
+
+    #[test]
+    fn writes_csv_and_ndjson() {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let options = ExportOptions::new(ExportFormat::Csv, ExportRows::Days, TrackingMode::Uptime);
+        let rows = export_rows_in(&[log()], &options, &utc);
+        assert_eq!(rows.len(), 3);
+
+        let mut csv = vec![];
+        write_rows(&rows, ExportFormat::Csv, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "game,\"Game, \"\"deluxe\"\"\",alice,2023-03-01,2023-03-02,7200,Initial"
+        );
+
+        let mut ndjson = vec![];
+        write_rows(&rows, ExportFormat::Ndjson, &mut ndjson).unwrap();
+        let first: serde_json::Value =
+            serde_json::from_str(String::from_utf8(ndjson).unwrap().lines().next().unwrap())
+                .unwrap();
+        assert_eq!(first["seconds"], 7200);
+        assert_eq!(first["badges"][0], "Initial");
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+use crate::error::TrackerError;
+use crate::storage::schema;
+
+use super::badges::use_badge_defs;
+use super::{Session, TrackLog, TrackingMode};
+
+/* Usage history from other trackers or another machine, merged into the apps tracked here */
+
+/// Reads a file of some format into track logs. Owner and running state of the result don't matter,
+/// [`merge_logs`] sets them
+pub trait Importer {
+    /// Name to pick the importer by
+    fn name(&self) -> &'static str;
+    /// File extensions the format usually has, without the dot
+    fn extensions(&self) -> &'static [&'static str];
+    fn parse(&self, data: &str) -> Result<Vec<TrackLog>, TrackerError>;
+}
+
+/// Stats file of this app, any schema version
+pub struct StatsImporter;
+
+impl Importer for StatsImporter {
+    fn name(&self) -> &'static str {
+        "stats"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn parse(&self, data: &str) -> Result<Vec<TrackLog>, TrackerError> {
+        schema::decode(data)
+    }
+}
+
+/// Rows of `app,start,end`. Times are unix seconds, RFC 3339 or local `YYYY-MM-DD HH:MM:SS`.
+/// A header row may name the columns in any order and add `display_name`, so session exports read back as well
+pub struct CsvImporter;
+
+impl Importer for CsvImporter {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["csv"]
+    }
+
+    fn parse(&self, data: &str) -> Result<Vec<TrackLog>, TrackerError> {
+        let mut lines = data
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .peekable();
+
+        let mut columns = CsvColumns::default();
+        if let Some((_, first)) = lines.peek() {
+            if let Some(header) = CsvColumns::from_header(&split_csv(first)) {
+                columns = header;
+                lines.next();
+            }
+        }
+
+        let mut logs: BTreeMap<String, TrackLog> = BTreeMap::new();
+        for (i, line) in lines {
+            let line_err = |msg: &str| TrackerError::Parse(format!("Line {}: {}", i + 1, msg));
+            let fields = split_csv(line);
+            let field = |index: usize| fields.get(index).map(|f| f.trim()).unwrap_or("");
+
+            let app = field(columns.app);
+            if app.is_empty() {
+                return Err(line_err("app is empty"));
+            }
+            let start =
+                parse_time(field(columns.start)).ok_or_else(|| line_err("invalid start time"))?;
+            let end = parse_time(field(columns.end)).ok_or_else(|| line_err("invalid end time"))?;
+            if end < start {
+                return Err(line_err("session ends before it starts"));
+            }
+
+            let log = logs.entry(app.to_owned()).or_insert_with(|| {
+                let display_name = columns.display_name.map(field).filter(|n| !n.is_empty());
+                TrackLog::new("", app, display_name.unwrap_or(app))
+            });
+            let mut session = Session::new(start);
+            session.extend(end, end - start);
+            log.sessions.push(session);
+        }
+        Ok(logs.into_values().collect())
+    }
+}
+
+/// Positions of the columns in a row
+struct CsvColumns {
+    app: usize,
+    start: usize,
+    end: usize,
+    display_name: Option<usize>,
+}
+
+impl Default for CsvColumns {
+    fn default() -> Self {
+        Self {
+            app: 0,
+            start: 1,
+            end: 2,
+            display_name: None,
+        }
+    }
+}
+
+impl CsvColumns {
+    fn from_header(fields: &[String]) -> Option<Self> {
+        let find = |name: &str| {
+            fields
+                .iter()
+                .position(|f| f.trim().eq_ignore_ascii_case(name))
+        };
+        Some(Self {
+            app: find("app")?,
+            start: find("start")?,
+            end: find("end")?,
+            display_name: find("display_name"),
+        })
+    }
+}
+
+/// Every format that can be imported
+pub fn importers() -> Vec<Box<dyn Importer>> {
+    vec![Box::new(StatsImporter), Box::new(CsvImporter)]
+}
+
+pub fn find_importer(name: &str) -> Option<Box<dyn Importer>> {
+    importers().into_iter().find(|i| i.name() == name)
+}
+
+/// Importer for the extension of the file
+pub fn importer_for_path(path: &Path) -> Option<Box<dyn Importer>> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    importers()
+        .into_iter()
+        .find(|i| i.extensions().contains(&ext.as_str()))
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub apps_added: usize,
+    pub apps_merged: usize,
+    pub sessions_added: usize,
+    /// Process names of apps that were added or got new sessions
+    pub changed: Vec<String>,
+}
+
+/// Merges imported apps into `apps` of `username`, matching them by process name.
+/// Sessions that are already there are skipped, so importing the same file twice changes nothing.
+/// Changed apps get the badges their new total in `mode` has reached, earned at `now`
+pub fn merge_logs(
+    apps: &mut Vec<TrackLog>,
+    imported: Vec<TrackLog>,
+    username: &str,
+    mode: TrackingMode,
+    now: u64,
+) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+
+    for other in imported {
+        let index = match apps
+            .iter()
+            .position(|l| l.process_name == other.process_name)
+        {
+            Some(i) => i,
+            None => {
+                let mut log = TrackLog::new(username, &other.process_name, &other.display_name);
+                // Counting starts once the app is seen running
+                log.is_running = false;
+                log.rules = other.rules.to_owned();
+                apps.push(log);
+                summary.apps_added += 1;
+                summary.changed.push(other.process_name.to_owned());
+                apps.len() - 1
+            }
+        };
+        let log = &mut apps[index];
+
+        let added = merge_sessions(&mut log.sessions, other.sessions)
+            + merge_sessions(&mut log.instances, other.instances);
+        if added == 0 {
+            continue;
+        }
+        summary.sessions_added += added;
+        if !summary.changed.contains(&log.process_name) {
+            summary.apps_merged += 1;
+            summary.changed.push(log.process_name.to_owned());
+        }
+
+        let badges = use_badge_defs().missed_badges(log, mode, username, now);
+        log.badges.extend(badges);
+    }
+    summary
+}
+
+/// Number of sessions added. Legacy totals have no dates to tell them apart,
+/// so another machine's total is added to the one legacy session
+fn merge_sessions(sessions: &mut Vec<Session>, other: Vec<Session>) -> usize {
+    let mut added = 0;
+    for session in other {
+        let is_known = sessions.iter().any(|s| {
+            *s == session
+                || (!s.legacy
+                    && !session.legacy
+                    && s.start == session.start
+                    && s.pid == session.pid)
+        });
+        if is_known || session.duration == 0 {
+            continue;
+        }
+        match sessions.iter_mut().find(|s| s.legacy && session.legacy) {
+            Some(legacy) => {
+                legacy.duration += session.duration;
+                legacy.focused += session.focused;
+            }
+            None => sessions.push(session),
+        }
+        added += 1;
+    }
+    added
+}
+
+fn parse_time(value: &str) -> Option<u64> {
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+    let secs = match DateTime::parse_from_rfc3339(value) {
+        Ok(time) => time.timestamp(),
+        Err(_) => {
+            let time = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").ok()?;
+            Local.from_local_datetime(&time).earliest()?.timestamp()
+        }
+    };
+    u64::try_from(secs).ok()
+}
+
+/// Fields of a CSV line. Quoted fields may contain commas and doubled quotes
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::{sqlite::SqliteStorage, StorageBackend};
+
+    use super::*;
+
+    fn session(start: u64, end: u64) -> Session {
+        let mut session = Session::new(start);
+        session.extend(end, end - start);
+        session
+    }
+
+    #[test]
+    fn parses_csv_with_or_without_header() {
+        let plain = "game,1000,4600\ngame,2023-03-01T10:00:00+00:00,2023-03-01T11:00:00+00:00\n";
+        let logs = CsvImporter.parse(plain).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].sessions.len(), 2);
+        assert_eq!(logs[0].uptime(), 7200);
+
+        let exported = "app,display_name,user,start,end,seconds,badges\n\
+                        game,\"Game, \"\"deluxe\"\"\",bob,100,200,100,Initial\n";
+        let logs = CsvImporter.parse(exported).unwrap();
+        assert_eq!(logs[0].display_name, "Game, \"deluxe\"");
+        assert_eq!(logs[0].sessions, vec![session(100, 200)]);
+
+        let err = CsvImporter.parse("game,200,100").unwrap_err();
+        assert!(err.to_string().starts_with("Line 1"));
+    }
+
+    #[test]
+    fn merges_by_process_name_once() {
+        let mut game = TrackLog::new("alice", "game", "Game");
+        game.sessions.push(session(0, 3600));
+        let mut apps = vec![game];
+
+        let mut other_game = TrackLog::new("bob", "game", "game");
+        other_game.sessions = vec![session(0, 3600), session(10_000, 13_600)];
+        let editor = TrackLog::new("bob", "editor", "Editor");
+        let imported = vec![other_game, editor];
+
+        let summary = merge_logs(
+            &mut apps,
+            imported.clone(),
+            "alice",
+            TrackingMode::Uptime,
+            0,
+        );
+        assert_eq!(
+            summary,
+            ImportSummary {
+                apps_added: 1,
+                apps_merged: 1,
+                sessions_added: 1,
+                changed: vec!["game".to_owned(), "editor".to_owned()],
+            }
+        );
+        assert_eq!(apps[0].uptime(), 7200);
+        // Habits depend on the local timezone, ranks don't
+        let ranks: Vec<&str> = apps[0]
+            .badges
+            .iter()
+            .map(|b| b.id.as_str())
+            .filter(|id| use_badge_defs().get(id).is_some())
+            .collect();
+        assert_eq!(ranks, vec!["Initial", "Common", "Rare"]);
+        assert_eq!(apps[1].username, "alice");
+        assert!(!apps[1].is_running);
+
+        let again = merge_logs(&mut apps, imported, "alice", TrackingMode::Uptime, 0);
+        assert_eq!(again, ImportSummary::default());
+    }
+
+    #[test]
+    fn sums_legacy_totals_of_two_machines() {
+        let legacy = |uptime: u64| {
+            let data = format!(
+                r#"[{{"username": "bob", "process_name": "game", "display_name": "Game", "uptime": {}, "is_running": false, "badges": []}}]"#,
+                uptime
+            );
+            StatsImporter.parse(&data).unwrap()
+        };
+        let mut apps = vec![];
+        merge_logs(&mut apps, legacy(3600), "alice", TrackingMode::Uptime, 0);
+        merge_logs(&mut apps, legacy(7200), "alice", TrackingMode::Uptime, 0);
+        assert_eq!(apps[0].uptime(), 10_800);
+        assert_eq!(apps[0].sessions.len(), 1);
+
+        let db = SqliteStorage::open_in_memory().unwrap();
+        db.save(&apps[0]).unwrap();
+        assert_eq!(db.load_all().unwrap()[0].uptime(), 10_800);
+    }
+}
@@ -0,0 +1,205 @@
+use std::fmt::Display;
+
+use chrono::{NaiveDate, TimeZone};
+use serde_derive::{Deserialize, Serialize};
+
+use super::usage::{usage_by_period_in, Bucket};
+use super::{TrackLog, TrackingMode};
+
+/* Daily and weekly time budgets of an app and the alerts fired when it gets close or runs out */
+
+/// Share of a limit at which the user is warned, in percent
+pub const WARNING_PERCENT: u64 = 80;
+
+/// Most time an app should be used, in seconds of the tracking mode. Unlimited if not set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UsageLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily: Option<u64>,
+    /// Week starts on Monday
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weekly: Option<u64>,
+}
+
+impl UsageLimits {
+    pub fn is_empty(&self) -> bool {
+        self.daily.is_none() && self.weekly.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitPeriod {
+    Daily,
+    Weekly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitLevel {
+    /// [`WARNING_PERCENT`] of the limit is used
+    Warning,
+    Reached,
+}
+
+/// App got close to or past one of its limits
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LimitAlert {
+    pub process_name: String,
+    pub display_name: String,
+    pub period: LimitPeriod,
+    pub level: LimitLevel,
+    /// First day of the period, YYYY-MM-DD
+    pub since: String,
+    pub used: u64,
+    pub limit: u64,
+    /// User closed the banner
+    #[serde(default)]
+    pub dismissed: bool,
+}
+
+impl LimitAlert {
+    /// Same limit and level in the same period. Such an alert fires once
+    pub fn is_same(&self, other: &LimitAlert) -> bool {
+        self.process_name == other.process_name
+            && self.period == other.period
+            && self.level == other.level
+            && self.since == other.since
+    }
+
+    pub fn message(&self) -> String {
+        let period = match self.period {
+            LimitPeriod::Daily => "daily",
+            LimitPeriod::Weekly => "weekly",
+        };
+        match self.level {
+            LimitLevel::Warning => format!(
+                "{} has used {}% of its {} limit ({} of {})",
+                self.display_name,
+                WARNING_PERCENT,
+                period,
+                format_limit(self.used),
+                format_limit(self.limit)
+            ),
+            LimitLevel::Reached => format!(
+                "{} has reached its {} limit of {}",
+                self.display_name,
+                period,
+                format_limit(self.limit)
+            ),
+        }
+    }
+}
+
+/// Alerts for the highest level reached of every limit, in the periods containing `now`
+pub fn check_limits_in<Tz: TimeZone>(
+    log: &TrackLog,
+    mode: TrackingMode,
+    now: u64,
+    tz: &Tz,
+) -> Vec<LimitAlert>
+where
+    Tz::Offset: Display,
+{
+    let Some(today) = tz
+        .timestamp_opt(now as i64, 0)
+        .earliest()
+        .map(|t| t.date_naive())
+    else {
+        return vec![];
+    };
+
+    [
+        (LimitPeriod::Daily, log.limits.daily, Bucket::Day),
+        (LimitPeriod::Weekly, log.limits.weekly, Bucket::Week),
+    ]
+    .into_iter()
+    .filter_map(|(period, limit, bucket)| {
+        let limit = limit?;
+        let since: NaiveDate = bucket.period_start(today);
+        let used = usage_by_period_in(std::slice::from_ref(log), bucket, since, today, mode, tz)
+            .first()
+            .map_or(0, |usage| usage.total());
+        let level = if used >= limit {
+            LimitLevel::Reached
+        } else if used * 100 >= limit * WARNING_PERCENT {
+            LimitLevel::Warning
+        } else {
+            return None;
+        };
+        Some(LimitAlert {
+            process_name: log.process_name.to_owned(),
+            display_name: log.display_name.to_owned(),
+            period,
+            level,
+            since: since.to_string(),
+            used,
+            limit,
+            dismissed: false,
+        })
+    })
+    .collect()
+}
+
+fn format_limit(secs: u64) -> String {
+    format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use crate::tracking::Session;
+
+    use super::*;
+
+    #[test]
+    fn alerts_at_warning_and_limit() {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        // Wednesday 2023-03-01 00:00 UTC
+        let midnight = 1_677_628_800;
+        let mut log = TrackLog::new("alice", "game", "Game");
+        log.limits = UsageLimits {
+            daily: Some(3600),
+            weekly: Some(4 * 3600),
+        };
+        let mut session = Session::new(midnight);
+        session.extend(midnight + 2880, 2880);
+        log.sessions.push(session);
+
+        let now = midnight + 3 * 3600;
+        let alerts = check_limits_in(&log, TrackingMode::Uptime, now, &utc);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(
+            (alerts[0].period, alerts[0].level, alerts[0].since.as_str()),
+            (LimitPeriod::Daily, LimitLevel::Warning, "2023-03-01")
+        );
+        assert_eq!(
+            alerts[0].message(),
+            "Game has used 80% of its daily limit (0h 48m of 1h 00m)"
+        );
+
+        // Monday of the same week
+        let mut earlier = Session::new(midnight - 2 * 86_400);
+        earlier.extend(midnight - 2 * 86_400 + 3 * 3600, 3 * 3600);
+        log.sessions.push(earlier);
+        log.sessions[0].extend(midnight + 3600, 720);
+        let alerts = check_limits_in(&log, TrackingMode::Uptime, now, &utc);
+        let levels: Vec<_> = alerts.iter().map(|a| (a.period, a.level)).collect();
+        assert_eq!(
+            levels,
+            vec![
+                (LimitPeriod::Daily, LimitLevel::Reached),
+                (LimitPeriod::Weekly, LimitLevel::Reached)
+            ]
+        );
+        assert_eq!(alerts[1].since, "2023-02-27");
+
+        // Daily limit starts over the next day
+        let next_day: Vec<_> = check_limits_in(&log, TrackingMode::Uptime, now + 86_400, &utc)
+            .iter()
+            .map(|a| a.period)
+            .collect();
+        assert_eq!(next_day, vec![LimitPeriod::Weekly]);
+    }
+}
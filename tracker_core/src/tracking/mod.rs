@@ -1,140 +1,291 @@
 pub mod badges;
-
+pub mod export;
+pub mod import;
+pub mod limits;
+pub mod profile;
+pub mod rules;
+#[cfg(test)]
+mod tests;
+pub mod usage;
+
+use chrono::Local;
 use serde_derive::{Deserialize, Serialize};
-use serde_json;
-use std::error::Error;
-use std::fs;
+use std::collections::HashMap;
 use std::sync::mpsc::{self, Sender, TryRecvError};
-use std::{fs::File, thread, time::Duration};
-
-use crate::store::apps_store::{use_apps_store, Actions};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::SystemTime;
+use std::{thread, time::Duration};
+
+use crate::config::use_config;
+use crate::notify::{default_notifier, Notifier};
+use crate::storage::{open_default_storage, StorageBackend};
+use crate::store::apps_store::{Actions, AppState, AppsStore};
 use crate::store::user_store::use_user_store;
-use crate::tracking::badges::get_badge;
+use crate::store::Store;
+use crate::sys::clock::{Clock, SystemClock};
+use crate::sys::watcher::ProcessWatcher;
+use crate::sys::{
+    use_focus_source, use_idle_source, use_process_notifier, use_process_source, FocusSource,
+    IdleSource, ProcessNotifier, ProcessSource,
+};
+use crate::tracking::badges::use_badge_defs;
 
 use self::badges::Badge;
+use self::limits::{check_limits_in, LimitAlert, UsageLimits};
+use self::rules::{AppMatcher, TrackRule};
 
-const STATS_PATH: &str = "./stats.json";
+lazy_static! {
+    static ref TRACKING_ENV: TrackingEnv = TrackingEnv::default();
+}
 
-pub fn get_tracked_procs_by_user(username: &str) -> Result<Vec<TrackLog>, Box<dyn Error>> {
-    let procs = get_stats_from_file()?;
-    Ok(procs
-        .into_iter()
-        .filter(|p| p.username == username)
-        .collect())
+/// Everything the tracking engine needs from the outside world. Tests swap it for fakes.
+#[derive(Clone)]
+pub struct TrackingEnv {
+    pub source: Arc<dyn ProcessSource>,
+    /// Wakes the supervisor early when processes start or exit
+    pub notifier: Arc<dyn ProcessNotifier>,
+    pub focus: Arc<dyn FocusSource>,
+    pub idle: Arc<dyn IdleSource>,
+    /// Counting stops after this long without input. `None` counts regardless
+    pub idle_timeout: Option<Duration>,
+    /// Record a session per process besides the app session
+    pub instance_sessions: bool,
+    /// Where earned badges and limit alerts are shown
+    pub notifications: Notifier,
+    pub clock: Arc<dyn Clock>,
+    pub storage: Arc<dyn StorageBackend>,
+}
+
+impl Default for TrackingEnv {
+    fn default() -> Self {
+        Self {
+            source: use_process_source(),
+            notifier: use_process_notifier(),
+            focus: use_focus_source(),
+            idle: use_idle_source(),
+            idle_timeout: use_config().idle_timeout(),
+            instance_sessions: use_config().instance_sessions,
+            notifications: default_notifier(),
+            clock: Arc::new(SystemClock),
+            storage: open_default_storage(),
+        }
+    }
+}
+
+pub fn use_tracking_env() -> TrackingEnv {
+    TRACKING_ENV.clone()
 }
 
-pub fn start_tracking<'a>(proc_name: &'a str) -> Sender<String> {
+/// Handle that engine threads use to reach the store they belong to
+#[derive(Clone)]
+pub struct Tracker {
+    pub env: TrackingEnv,
+    store: Weak<Mutex<Store<AppState, Actions>>>,
+}
+
+impl Tracker {
+    pub fn new(env: TrackingEnv, store: Weak<Mutex<Store<AppState, Actions>>>) -> Self {
+        Self { env, store }
+    }
+
+    pub fn store(&self) -> Option<AppsStore> {
+        self.store.upgrade()
+    }
+
+    /// Spawns a thread the clock knows about, so a manual clock can wait for it
+    pub fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        struct Guard(Arc<dyn Clock>);
+        impl Drop for Guard {
+            fn drop(&mut self) {
+                self.0.thread_finished();
+            }
+        }
+
+        self.env.clock.thread_started();
+        let guard = Guard(self.env.clock.clone());
+        thread::spawn(move || {
+            let _guard = guard;
+            f();
+        });
+    }
+}
+
+pub fn start_tracking(tracker: &Tracker, proc_name: &str) -> Sender<String> {
     println!("Started tracking: {}", &proc_name);
-    start_tracker_thread_for_proc(proc_name)
+    start_tracker_thread_for_proc(tracker, proc_name)
 }
-/// Query running processes and if the number is changed, check if need to start tracking a process
-pub fn start_supervisor_thread() {
-    thread::spawn(move || {
+/// Watch processes start and exit, match them against rules of tracked apps and resume the ones that were launched.
+/// Also finds out which tracked app owns the focused window.
+/// Rechecks as soon as the OS reports a change, or every few seconds without notifications
+pub fn start_supervisor_thread(tracker: Tracker) {
+    let clock = tracker.env.clock.clone();
+    let focus = tracker.env.focus.clone();
+    let notifier = tracker.env.notifier.clone();
+    let mut watcher = ProcessWatcher::new(tracker.env.source.clone());
+    let store = match tracker.store() {
+        Some(store) => store,
+        None => return,
+    };
+
+    tracker.spawn(move || {
         let interval = Duration::from_secs(3);
-        let mut prev_proc_num: u16 = 0;
+        // Lets a burst of launches settle into a single check
+        let debounce = Duration::from_millis(200);
+        // Invalid rules and failing process listing are reported once, not on every check
+        let mut rule_errors: Vec<String> = vec![];
+        let mut poll_error: Option<String> = None;
 
         loop {
-            // Query and update store with currently running procs
-            use_apps_store()
+            // Apply started and exited procs to the store
+            match watcher.poll() {
+                Ok(events) => {
+                    poll_error = None;
+                    if !events.is_empty() {
+                        store
+                            .lock()
+                            .unwrap()
+                            .dispatch(Actions::ProcessEvents(events));
+                    }
+                }
+                Err(e) => {
+                    let msg = e.to_string();
+                    if poll_error.as_ref() != Some(&msg) {
+                        store.lock().unwrap().dispatch(Actions::ReportError(format!(
+                            "Couldn't get running processes. {}",
+                            msg
+                        )));
+                        poll_error = Some(msg);
+                    }
+                }
+            }
+
+            let (procs, tracked) = {
+                let mut store = store.lock().unwrap();
+                let state = store.selector();
+                (state.untracked_apps.clone(), state.tracked_apps.clone())
+            };
+            let matcher = AppMatcher::new(&tracked);
+            if matcher.errors() != rule_errors.as_slice() {
+                rule_errors = matcher.errors().to_vec();
+                if !rule_errors.is_empty() {
+                    store.lock().unwrap().dispatch(Actions::ReportError(format!(
+                        "Invalid tracking rules. {}",
+                        rule_errors.join(". ")
+                    )));
+                }
+            }
+            // No display server or a window without pid means nothing is focused
+            let focused = focus
+                .focused_pid()
+                .ok()
+                .flatten()
+                .and_then(|pid| procs.iter().find(|p| p.pid == pid))
+                .and_then(|p| matcher.app_for(p));
+            store
                 .lock()
                 .unwrap()
-                .dispatch(Actions::QueryUntrackedApps);
-
-            thread::sleep(interval);
-
-            let proc_num = use_apps_store()
+                .dispatch(Actions::UpdateRunningApps(matcher.running_apps(&procs)));
+            store
                 .lock()
                 .unwrap()
-                .selector()
-                .untracked_apps
-                .len() as u16;
+                .dispatch(Actions::UpdateFocusedApp(focused));
+
+            if notifier.wait(interval) {
+                clock.sleep(debounce);
+            }
 
             // Check if any of tracked procs launched to resume tracking
-            if proc_num != prev_proc_num {
-                let untracked = use_apps_store()
-                    .lock()
-                    .unwrap()
-                    .selector()
-                    .untracked_apps
-                    .clone();
-                let tracked = use_apps_store()
-                    .lock()
-                    .unwrap()
-                    .selector()
-                    .tracked_apps
-                    .clone();
-
-                tracked.into_iter().for_each(|l| {
-                    if !l.is_running {
-                        let is_restarted = untracked
-                            .iter()
-                            .find(|p| p.name == l.process_name)
-                            .is_some();
-
-                        if is_restarted {
-                            use_apps_store()
-                                .lock()
-                                .unwrap()
-                                .dispatch(Actions::ResumeTracking(l.process_name.to_owned()))
-                        }
-                    };
-                });
-
-                prev_proc_num = proc_num;
+            let (running, tracked) = {
+                let mut store = store.lock().unwrap();
+                let state = store.selector();
+                (state.running_apps.clone(), state.tracked_apps.clone())
+            };
+            for l in tracked {
+                if !l.is_running && !l.paused && running.contains_key(&l.process_name) {
+                    store
+                        .lock()
+                        .unwrap()
+                        .dispatch(Actions::ResumeTracking(l.process_name.to_owned()))
+                }
             }
         }
     });
 }
 
-fn start_tracker_thread_for_proc(proc_name: &str) -> Sender<String> {
+fn start_tracker_thread_for_proc(tracker: &Tracker, proc_name: &str) -> Sender<String> {
     let (rx, tx) = mpsc::channel();
     let proc_name = proc_name.to_owned();
+    let clock = tracker.env.clock.clone();
+    let idle_source = tracker.env.idle.clone();
+    let idle_timeout = tracker.env.idle_timeout;
+    let record_instances = tracker.env.instance_sessions;
+    let store = match tracker.store() {
+        Some(store) => store,
+        None => return rx,
+    };
 
-    thread::spawn(move || {
-        fn check_is_proc_running(proc_name: &str) -> bool {
-            use_apps_store()
-                .lock()
-                .unwrap()
-                .selector()
-                .untracked_apps
-                .clone()
-                .into_iter()
-                .find(|p| p.name == proc_name)
-                .is_some()
+    tracker.spawn(move || {
+        /// Live processes of the app as pid and OS start time
+        fn live_instances(store: &AppsStore, proc_name: &str) -> Vec<(u32, Option<u64>)> {
+            let mut store = store.lock().unwrap();
+            let state = store.selector();
+            let Some(pids) = state.running_apps.get(proc_name) else {
+                return vec![];
+            };
+            pids.iter()
+                .map(|pid| {
+                    let started = state
+                        .untracked_apps
+                        .iter()
+                        .find(|p| p.pid == *pid)
+                        .and_then(|p| p.started_at)
+                        .map(unix_secs);
+                    (*pid, started)
+                })
+                .collect()
         }
 
         let interval = Duration::from_secs(5);
         let mut elapsed: u64 = 0;
+        let idle_for = || {
+            idle_timeout.and_then(|timeout| {
+                let idle = idle_source.idle_time().unwrap_or_default();
+                (idle >= timeout).then_some(idle)
+            })
+        };
 
-        let store = use_apps_store().to_owned();
-        let mut prev_proc_num = store.lock().unwrap().selector().untracked_apps.len() as u16;
-
-        /* check if process was added earlier  */
-        let stored_data = store
+        /* Count from when the processes started, not from when we noticed them,
+         * and only up to the last input if the user is already away */
+        let away = idle_for();
+        let mut is_idle = away.is_some();
+        let now = unix_secs(clock.now()).saturating_sub(away.map_or(0, |idle| idle.as_secs()));
+        let live = live_instances(&store, &proc_name);
+        let log = store
             .lock()
             .unwrap()
             .selector()
             .tracked_apps
-            .clone()
-            .into_iter()
-            .find(|p| p.process_name == proc_name);
-
-        let mut total_time: u64 = if stored_data.is_none() {
-            0
-        } else {
-            let t = stored_data.as_ref().unwrap();
-            t.uptime
-        };
-
-        let mut is_running = check_is_proc_running(&proc_name);
+            .iter()
+            .find(|l| l.process_name == proc_name)
+            .cloned()
+            .unwrap_or_else(|| TrackLog::new("", &proc_name, &proc_name));
+        let first_start = live.iter().filter_map(|(_, started)| *started).min();
+        let mut session = log.resume_session(first_start, now);
+        let mut instances: HashMap<u32, Session> = HashMap::new();
+        if record_instances {
+            for (pid, started) in &live {
+                instances.insert(*pid, log.resume_instance(*pid, *started, now));
+            }
+        }
 
         loop {
-            let proc_num = store.lock().unwrap().selector().untracked_apps.len() as u16;
-
-            if prev_proc_num != proc_num {
-                is_running = check_is_proc_running(&proc_name);
-                prev_proc_num = proc_num;
-            }
+            let live = live_instances(&store, &proc_name);
+            let is_running = !live.is_empty();
+            let is_focused =
+                store.lock().unwrap().selector().focused_app.as_ref() == Some(&proc_name);
             /* Check if user terminated tracking (deleted by user) */
             match tx.try_recv() {
                 Ok(_) | Err(TryRecvError::Disconnected) => {
@@ -143,12 +294,46 @@ fn start_tracker_thread_for_proc(proc_name: &str) -> Sender<String> {
                 }
                 Err(TryRecvError::Empty) => {}
             };
+            /* User went away. Time since the last input is cut off and the rest is a gap until they're back */
+            match (idle_for(), is_idle) {
+                (Some(idle), false) => {
+                    println!("User is idle. Pausing: {}", proc_name);
+                    session.rewind(idle.as_secs());
+                    for instance in instances.values_mut() {
+                        instance.rewind(idle.as_secs());
+                    }
+                    is_idle = true;
+                }
+                (None, true) => {
+                    println!("User is back. Resuming: {}", proc_name);
+                    session = Session::new(unix_secs(clock.now()));
+                    instances.clear();
+                    is_idle = false;
+                }
+                _ => (),
+            }
+            /* Follow instances that started and forget the ones that exited */
+            if record_instances && !is_idle {
+                instances.retain(|pid, _| live.iter().any(|(p, _)| p == pid));
+                for (pid, started) in &live {
+                    instances.entry(*pid).or_insert_with(|| {
+                        Session::new(unix_secs(clock.now())).for_instance(*pid, *started)
+                    });
+                }
+            }
             /* Save uptime if process is still running, else save and break */
             if is_running {
-                store
-                    .lock()
-                    .unwrap()
-                    .dispatch(Actions::UpdateAppTime(proc_name.to_owned(), total_time));
+                store.lock().unwrap().dispatch(Actions::UpdateSession(
+                    proc_name.to_owned(),
+                    session.clone(),
+                ));
+                for instance in instances.values() {
+                    store.lock().unwrap().dispatch(Actions::UpdateInstance(
+                        proc_name.to_owned(),
+                        instance.clone(),
+                    ));
+                }
+                alert_limits(&store, &proc_name, unix_secs(clock.now()));
             } else {
                 store
                     .lock()
@@ -160,7 +345,7 @@ fn start_tracker_thread_for_proc(proc_name: &str) -> Sender<String> {
                     .dispatch(Actions::SaveData(proc_name.to_owned()));
                 break;
             }
-            if elapsed % 120 == 0 {
+            if elapsed.is_multiple_of(120) {
                 store
                     .lock()
                     .unwrap()
@@ -168,114 +353,367 @@ fn start_tracker_thread_for_proc(proc_name: &str) -> Sender<String> {
             };
 
             /* Check badges */
-            if elapsed % 300 == 0 {
-                let badge = get_badge(total_time, &use_user_store().selector().username);
-
-                if badge.is_some() {
-                    store.lock().unwrap().dispatch(Actions::AddBadgeToProc(
-                        badge.unwrap(),
-                        proc_name.to_owned(),
-                    ));
+            if elapsed.is_multiple_of(300) {
+                let (username, mode) = {
+                    let mut user_store = use_user_store();
+                    let user = user_store.selector();
+                    (user.username.to_owned(), user.tracking_mode)
+                };
+                let now = unix_secs(clock.now());
+                let badges = store
+                    .lock()
+                    .unwrap()
+                    .selector()
+                    .tracked_apps
+                    .iter()
+                    .find(|l| l.process_name == proc_name)
+                    .map_or(vec![], |l| {
+                        use_badge_defs().new_badges(l, mode, &username, now)
+                    });
+
+                for badge in badges {
+                    store
+                        .lock()
+                        .unwrap()
+                        .dispatch(Actions::AddBadgeToProc(badge, proc_name.to_owned()));
                 }
             }
 
-            thread::sleep(interval);
+            clock.sleep(interval);
             elapsed += interval.as_secs();
-            total_time += interval.as_secs();
+            if !is_idle {
+                session.extend(unix_secs(clock.now()), interval.as_secs());
+                for instance in instances.values_mut() {
+                    instance.extend(unix_secs(clock.now()), interval.as_secs());
+                }
+                if is_focused {
+                    session.add_focused(interval.as_secs());
+                }
+            }
         }
         println!("Tracking thread for: {} terminated", proc_name);
     });
     rx
 }
-/// Returns locally saved stats in form of vector.
-fn get_stats_from_file() -> Result<Vec<TrackLog>, Box<dyn Error>> {
-    File::open(STATS_PATH)?;
-
-    let data = fs::read_to_string(STATS_PATH).expect("Unable to read file");
-    let mut stats: Vec<TrackLog> = Vec::new();
-    if data.trim().len() != 0 {
-        stats = serde_json::from_str::<Vec<TrackLog>>(&data)?;
+/// Fires alerts for limits of the app that were reached since the last check
+fn alert_limits(store: &AppsStore, proc_name: &str, now: u64) {
+    let mode = use_user_store().selector().tracking_mode;
+    let mut store = store.lock().unwrap();
+    let state = store.selector();
+    let Some(log) = state
+        .tracked_apps
+        .iter()
+        .find(|l| l.process_name == proc_name)
+    else {
+        return;
     };
-    Ok(stats)
+    if log.limits.is_empty() {
+        return;
+    }
+    let alerts: Vec<LimitAlert> = check_limits_in(log, mode, now, &Local)
+        .into_iter()
+        .filter(|alert| !state.limit_alerts.iter().any(|a| a.is_same(alert)))
+        .collect();
+
+    for alert in alerts {
+        store.dispatch(Actions::AddLimitAlert(alert));
+    }
+}
+
+/// Seconds since unix epoch. Times before the epoch are clamped to 0
+pub fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Single run of a tracked process. Times are unix seconds
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Session {
+    pub start: u64,
+    pub end: u64,
+    pub duration: u64, // seconds
+    /// Part of the duration the app owned the foreground window
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub focused: u64,
+    /// Total uptime carried over from files written before sessions existed. Start and end are unknown
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub legacy: bool,
+    /// Process the session belongs to. Only set for instance sessions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// When the OS started that process, unix seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proc_start: Option<u64>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// What counts as using an app in badges and reports
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackingMode {
+    /// Whole time the app is running
+    #[default]
+    Uptime,
+    /// Only time its window has focus
+    Focused,
+}
+
+impl Session {
+    pub fn new(start: u64) -> Self {
+        Self {
+            start,
+            end: start,
+            duration: 0,
+            focused: 0,
+            legacy: false,
+            pid: None,
+            proc_start: None,
+        }
+    }
+
+    /// Session of a single process rather than the whole app
+    pub fn for_instance(mut self, pid: u32, proc_start: Option<u64>) -> Self {
+        self.pid = Some(pid);
+        self.proc_start = proc_start;
+        self
+    }
+
+    pub fn legacy(duration: u64) -> Self {
+        Self {
+            start: 0,
+            end: 0,
+            duration,
+            focused: 0,
+            legacy: true,
+            pid: None,
+            proc_start: None,
+        }
+    }
+
+    pub fn extend(&mut self, now: u64, seconds: u64) {
+        self.end = now.max(self.end);
+        self.duration += seconds;
+    }
+
+    /// Takes back the last `seconds`, e.g. the time the user was already away
+    pub fn rewind(&mut self, seconds: u64) {
+        let seconds = seconds.min(self.duration);
+        self.end = self.end.saturating_sub(seconds).max(self.start);
+        self.duration -= seconds;
+        self.focused = self.focused.min(self.duration);
+    }
+
+    pub fn add_focused(&mut self, seconds: u64) {
+        self.focused = (self.focused + seconds).min(self.duration);
+    }
+
+    /// Seconds counted in the given mode
+    pub fn time(&self, mode: TrackingMode) -> u64 {
+        match mode {
+            TrackingMode::Uptime => self.duration,
+            TrackingMode::Focused => self.focused,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(from = "RawTrackLog", into = "RawTrackLog")]
 pub struct TrackLog {
     pub username: String,
-    pub uptime: u64, // seconds
+    pub sessions: Vec<Session>,
     pub badges: Vec<Badge>,
     pub process_name: String,
     pub display_name: String,
     pub is_running: bool,
+    /// Not tracked even while running, until the user resumes it
+    pub paused: bool,
+    /// Which processes count as this app. Exact `process_name` if empty
+    pub rules: Vec<TrackRule>,
+    /// Sessions of single processes. Overlap each other and the app sessions, so they don't add to totals
+    pub instances: Vec<Session>,
+    pub limits: UsageLimits,
+}
+
+/// On-disk form of [`TrackLog`]. Old files have only `uptime`, new ones keep it as a readable total
+#[derive(Deserialize, Serialize)]
+struct RawTrackLog {
+    username: String,
+    #[serde(default)]
+    uptime: u64, // seconds
+    #[serde(default)]
+    sessions: Vec<Session>,
+    badges: Vec<Badge>,
+    process_name: String,
+    display_name: String,
+    is_running: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    paused: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    rules: Vec<TrackRule>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    instances: Vec<Session>,
+    #[serde(default, skip_serializing_if = "UsageLimits::is_empty")]
+    limits: UsageLimits,
+}
+
+impl From<RawTrackLog> for TrackLog {
+    fn from(raw: RawTrackLog) -> Self {
+        let mut sessions = raw.sessions;
+        if sessions.is_empty() && raw.uptime > 0 {
+            sessions.push(Session::legacy(raw.uptime));
+        }
+        TrackLog {
+            username: raw.username,
+            sessions,
+            badges: raw.badges,
+            process_name: raw.process_name,
+            display_name: raw.display_name,
+            is_running: raw.is_running,
+            paused: raw.paused,
+            rules: raw.rules,
+            instances: raw.instances,
+            limits: raw.limits,
+        }
+    }
+}
+
+impl From<TrackLog> for RawTrackLog {
+    fn from(log: TrackLog) -> Self {
+        RawTrackLog {
+            uptime: log.uptime(),
+            username: log.username,
+            sessions: log.sessions,
+            badges: log.badges,
+            process_name: log.process_name,
+            display_name: log.display_name,
+            is_running: log.is_running,
+            paused: log.paused,
+            rules: log.rules,
+            instances: log.instances,
+            limits: log.limits,
+        }
+    }
 }
 
 impl TrackLog {
     pub fn new(username: &str, proc_name: &str, display_name: &str) -> Self {
         TrackLog {
             username: String::from(username),
-            uptime: 0,
+            sessions: vec![],
             badges: vec![],
             process_name: String::from(proc_name),
             display_name: display_name.to_owned(),
             is_running: true, // assumes when we create track log, process is running in sys
+            paused: false,
+            rules: vec![],
+            instances: vec![],
+            limits: UsageLimits::default(),
         }
     }
 
-    pub fn add_uptime(&mut self, seconds: u64) {
-        self.uptime += seconds;
+    /// Rules used for matching. Apps without explicit rules match their process name
+    pub fn effective_rules(&self) -> Vec<TrackRule> {
+        if self.rules.is_empty() {
+            vec![TrackRule::exact_name(&self.process_name)]
+        } else {
+            self.rules.to_owned()
+        }
     }
 
-    pub fn set_uptime(&mut self, seconds: u64) {
-        self.uptime = seconds;
+    /// Total seconds across all sessions
+    pub fn uptime(&self) -> u64 {
+        self.sessions.iter().map(|s| s.duration).sum()
     }
 
-    pub fn set_display_name(&mut self, new_name: &str) {
-        self.display_name = new_name.to_owned();
+    /// Total seconds the app had focus
+    pub fn focused_time(&self) -> u64 {
+        self.sessions.iter().map(|s| s.focused).sum()
     }
 
-    pub fn delete_from_file(&self) -> Result<(), Box<dyn Error>> {
-        let mut prev_stats = get_stats_from_file()?;
+    pub fn time(&self, mode: TrackingMode) -> u64 {
+        match mode {
+            TrackingMode::Uptime => self.uptime(),
+            TrackingMode::Focused => self.focused_time(),
+        }
+    }
 
-        prev_stats.retain(|log| log.process_name != self.process_name);
-        let serialized = serde_json::to_string_pretty(&prev_stats)?;
-        fs::write(STATS_PATH, serialized)?;
+    /// Most recent session, with known start time
+    pub fn last_session(&self) -> Option<&Session> {
+        self.sessions
+            .iter()
+            .filter(|s| !s.legacy)
+            .max_by_key(|s| s.start)
+    }
 
-        Ok(())
+    /// Session for a run of the app whose first process started at `proc_start`, counted up to `now`.
+    /// Continues the last session if it already covers part of the run, e.g. before the tracker restarted
+    pub fn resume_session(&self, proc_start: Option<u64>, now: u64) -> Session {
+        resume_from(self.last_session(), proc_start, now)
     }
 
-    pub fn save_to_file(&self) -> Result<(), Box<dyn Error>> {
-        let mut prev_stats = match get_stats_from_file() {
-            Ok(data) => data,
-            Err(_) => {
-                if let Err(e) = File::create(STATS_PATH) {
-                    eprintln!("Couldn't create a stat file: {}", e);
-                };
-                Vec::new()
-            }
-        };
-        // if track log is already in file
-        let mut is_in_file = false;
-        for ind in 0..prev_stats.len() {
-            let curr = &prev_stats[ind];
-            if curr.process_name == self.process_name {
-                prev_stats[ind].set_uptime(self.uptime);
-                prev_stats[ind].set_display_name(&self.display_name);
-                prev_stats[ind].badges = self.badges.to_owned();
-                prev_stats[ind].is_running = false;
-                is_in_file = true;
-                break;
-            } else {
-                is_in_file = false;
-            }
-        }
-        if !is_in_file {
-            prev_stats.push(self.clone())
-        };
+    /// Same as [`TrackLog::resume_session`] for a single process
+    pub fn resume_instance(&self, pid: u32, proc_start: Option<u64>, now: u64) -> Session {
+        let last = self
+            .instances
+            .iter()
+            .filter(|s| s.pid == Some(pid) && s.proc_start == proc_start)
+            .max_by_key(|s| s.start);
+        resume_from(last, proc_start, now).for_instance(pid, proc_start)
+    }
 
-        let serialized = serde_json::to_string_pretty(&prev_stats)?;
+    /// Replaces the session that started at the same time or adds a new one.
+    /// Empty sessions are not recorded
+    pub fn update_session(&mut self, session: Session) {
+        upsert_session(&mut self.sessions, session);
+    }
 
-        fs::write(STATS_PATH, serialized)?;
+    /// Same as [`TrackLog::update_session`] for the session of a single process
+    pub fn update_instance(&mut self, session: Session) {
+        upsert_session(&mut self.instances, session);
+    }
 
-        Ok(())
+    pub fn set_display_name(&mut self, new_name: &str) {
+        self.display_name = new_name.to_owned();
+    }
+}
+
+fn resume_from(last: Option<&Session>, proc_start: Option<u64>, now: u64) -> Session {
+    let proc_start = match proc_start {
+        Some(start) if start < now => start,
+        // Unknown start time or a clock that went back
+        _ => return Session::new(now),
+    };
+    match last {
+        Some(last) if last.end > proc_start => {
+            let mut session = last.clone();
+            session.extend(now, now.saturating_sub(last.end));
+            session
+        }
+        _ => {
+            let mut session = Session::new(proc_start);
+            session.extend(now, now - proc_start);
+            session
+        }
+    }
+}
+
+fn upsert_session(sessions: &mut Vec<Session>, session: Session) {
+    match sessions
+        .iter()
+        .position(|s| !s.legacy && s.start == session.start && s.pid == session.pid)
+    {
+        Some(i) if session.duration == 0 => {
+            sessions.remove(i);
+        }
+        Some(i) => sessions[i] = session,
+        None if session.duration > 0 => sessions.push(session),
+        None => (),
     }
 }
@@ -0,0 +1,456 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDate, TimeZone};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::config::use_config;
+use crate::error::TrackerError;
+use crate::storage::json::write_atomic;
+
+use super::badges::{parse_color, Progress, Rgb};
+use super::usage::{daily_usage_in, usage_by_period_in, Bucket};
+use super::{TrackLog, TrackingMode};
+
+/* Achievements of the user over every tracked app, kept in `profile.json` next to the stats */
+
+const PROFILE_FILE: &str = "profile.json";
+
+/// What a user-level achievement asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlobalGoal {
+    /// Hours of all apps together
+    TotalHours(u64),
+    /// Apps being tracked
+    AppsTracked(u64),
+    /// Time of the current month spread over at least `min_apps`, none taking more than `max_percent`
+    Balanced { min_apps: usize, max_percent: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalAchievement {
+    pub id: &'static str,
+    pub goal: GlobalGoal,
+    pub icon: &'static str,
+    /// `#rrggbb`
+    pub color: &'static str,
+    pub description: &'static str,
+}
+
+impl GlobalAchievement {
+    pub fn rgb(&self) -> Option<Rgb> {
+        parse_color(self.color)
+    }
+}
+
+pub fn global_achievements() -> Vec<GlobalAchievement> {
+    let achievement = |id, goal, icon, color, description| GlobalAchievement {
+        id,
+        goal,
+        icon,
+        color,
+        description,
+    };
+    vec![
+        achievement(
+            "Dedicated",
+            GlobalGoal::TotalHours(100),
+            "🎯",
+            "#e59866",
+            "One hundred hours over all your apps.",
+        ),
+        achievement(
+            "Devoted",
+            GlobalGoal::TotalHours(1000),
+            "🏆",
+            "#f4d03f",
+            "One thousand hours over all your apps.",
+        ),
+        achievement(
+            "Explorer",
+            GlobalGoal::AppsTracked(5),
+            "🧭",
+            "#48c9b0",
+            "Five apps tracked.",
+        ),
+        achievement(
+            "Collector",
+            GlobalGoal::AppsTracked(10),
+            "📚",
+            "#5499c7",
+            "Ten apps tracked.",
+        ),
+        achievement(
+            "Balanced",
+            GlobalGoal::Balanced {
+                min_apps: 3,
+                max_percent: 50,
+            },
+            "⚖",
+            "#af7ac5",
+            "Three or more apps this month and none took over half of the time.",
+        ),
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EarnedAchievement {
+    pub id: String,
+    /// Unix seconds
+    pub earned_at: u64,
+}
+
+/// App used the most in a calendar month
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MonthlyFavourite {
+    /// YYYY-MM
+    pub month: String,
+    pub process_name: String,
+    pub display_name: String,
+    pub seconds: u64,
+}
+
+/// Achievement with how far the user got. Earned ones stay earned
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(into = "ProgressById", try_from = "ProgressById")]
+pub struct AchievementProgress {
+    pub achievement: GlobalAchievement,
+    pub progress: Progress,
+    pub earned_at: Option<u64>,
+}
+
+/// Achievement progress as it's sent to clients of the daemon
+#[derive(Deserialize, Serialize)]
+struct ProgressById {
+    id: String,
+    progress: Progress,
+    earned_at: Option<u64>,
+}
+
+impl From<AchievementProgress> for ProgressById {
+    fn from(p: AchievementProgress) -> Self {
+        Self {
+            id: p.achievement.id.to_owned(),
+            progress: p.progress,
+            earned_at: p.earned_at,
+        }
+    }
+}
+
+impl TryFrom<ProgressById> for AchievementProgress {
+    type Error = String;
+
+    fn try_from(p: ProgressById) -> Result<Self, Self::Error> {
+        let achievement = global_achievements()
+            .into_iter()
+            .find(|a| a.id == p.id)
+            .ok_or_else(|| format!("Unknown achievement {}", p.id))?;
+        Ok(Self {
+            achievement,
+            progress: p.progress,
+            earned_at: p.earned_at,
+        })
+    }
+}
+
+/// Only achievements and favourites are kept in the file, the rest is worked out on every update
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct UserProfile {
+    #[serde(default)]
+    pub achievements: Vec<EarnedAchievement>,
+    /// Oldest month first. The current month changes until it's over
+    #[serde(default)]
+    pub favourites: Vec<MonthlyFavourite>,
+    /// Time of every app together in the tracking mode, as of the last update
+    #[serde(default)]
+    pub total_secs: u64,
+    #[serde(default)]
+    pub apps_tracked: usize,
+    #[serde(default)]
+    pub progress: Vec<AchievementProgress>,
+}
+
+/// Part of the profile in `profile.json`
+#[derive(Serialize)]
+struct SavedProfile<'a> {
+    achievements: &'a [EarnedAchievement],
+    favourites: &'a [MonthlyFavourite],
+}
+
+impl UserProfile {
+    /// Missing file gives an empty profile
+    pub fn load(path: &Path) -> Result<Self, TrackerError> {
+        match fs::read_to_string(path) {
+            Ok(data) => Ok(serde_json::from_str(&data)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TrackerError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let saved = SavedProfile {
+            achievements: &self.achievements,
+            favourites: &self.favourites,
+        };
+        write_atomic(path, serde_json::to_string_pretty(&saved)?.as_bytes())
+    }
+
+    /// Same as [`UserProfile::update_in`] in the local timezone
+    pub fn update(
+        &mut self,
+        logs: &[TrackLog],
+        mode: TrackingMode,
+        now: u64,
+    ) -> Vec<GlobalAchievement> {
+        self.update_in(logs, mode, now, &Local)
+    }
+
+    /// Works the profile out again from the apps. Returns achievements earned by this update
+    pub fn update_in<Tz: TimeZone>(
+        &mut self,
+        logs: &[TrackLog],
+        mode: TrackingMode,
+        now: u64,
+        tz: &Tz,
+    ) -> Vec<GlobalAchievement> {
+        self.total_secs = logs.iter().map(|l| l.time(mode)).sum();
+        self.apps_tracked = logs.len();
+        let month = MonthUsage::in_month(logs, mode, now, tz);
+        let history = monthly_favourites_in(logs, mode, tz);
+        // Months of apps that are no longer tracked can't be worked out again
+        self.favourites
+            .retain(|f| !history.iter().any(|h| h.month == f.month));
+        self.favourites.extend(history);
+        self.favourites.sort_by(|a, b| a.month.cmp(&b.month));
+
+        let mut earned = vec![];
+        self.progress = global_achievements()
+            .into_iter()
+            .map(|achievement| {
+                let progress = self.goal_progress(achievement.goal, &month);
+                let mut earned_at = self
+                    .achievements
+                    .iter()
+                    .find(|a| a.id == achievement.id)
+                    .map(|a| a.earned_at);
+                if earned_at.is_none() && progress.is_complete() {
+                    self.achievements.push(EarnedAchievement {
+                        id: achievement.id.to_owned(),
+                        earned_at: now,
+                    });
+                    earned_at = Some(now);
+                    earned.push(achievement.clone());
+                }
+                AchievementProgress {
+                    achievement,
+                    progress,
+                    earned_at,
+                }
+            })
+            .collect();
+        earned
+    }
+
+    fn goal_progress(&self, goal: GlobalGoal, month: &MonthUsage) -> Progress {
+        match goal {
+            GlobalGoal::TotalHours(hours) => Progress::new(self.total_secs / 3600, hours),
+            GlobalGoal::AppsTracked(apps) => Progress::new(self.apps_tracked as u64, apps),
+            GlobalGoal::Balanced {
+                min_apps,
+                max_percent,
+            } => {
+                let used = month.seconds.iter().filter(|secs| **secs > 0).count();
+                let top = month.seconds.iter().max().copied().unwrap_or(0);
+                let total: u64 = month.seconds.iter().sum();
+                let is_balanced = used >= min_apps && top * 100 <= total * max_percent;
+                Progress::new(is_balanced as u64, 1)
+            }
+        }
+    }
+
+    /// Most used app of a month, YYYY-MM
+    pub fn favourite_of(&self, month: &str) -> Option<&MonthlyFavourite> {
+        self.favourites.iter().find(|f| f.month == month)
+    }
+}
+
+/// Time of every app in the current month
+struct MonthUsage {
+    seconds: Vec<u64>,
+}
+
+impl MonthUsage {
+    fn in_month<Tz: TimeZone>(logs: &[TrackLog], mode: TrackingMode, now: u64, tz: &Tz) -> Self {
+        let today = tz
+            .timestamp_opt(now as i64, 0)
+            .earliest()
+            .map_or(NaiveDate::MIN, |t| t.date_naive());
+        let start = Bucket::Month.period_start(today);
+        let usage = usage_by_period_in(logs, Bucket::Month, start, today, mode, tz);
+        Self {
+            seconds: usage.iter().map(|u| u.total()).collect(),
+        }
+    }
+}
+
+/// Most used app of every month in the sessions, oldest month first
+fn monthly_favourites_in<Tz: TimeZone>(
+    logs: &[TrackLog],
+    mode: TrackingMode,
+    tz: &Tz,
+) -> Vec<MonthlyFavourite> {
+    // Seconds of every app per month, apps in the order of `logs`
+    let mut months: BTreeMap<NaiveDate, Vec<u64>> = BTreeMap::new();
+    for (i, log) in logs.iter().enumerate() {
+        for (day, seconds) in daily_usage_in(log, mode, tz) {
+            let month = months
+                .entry(Bucket::Month.period_start(day))
+                .or_insert_with(|| vec![0; logs.len()]);
+            month[i] += seconds;
+        }
+    }
+    months
+        .into_iter()
+        .filter_map(|(start, seconds)| {
+            let (i, secs) = seconds.iter().enumerate().max_by_key(|(_, secs)| **secs)?;
+            Some(MonthlyFavourite {
+                month: start.format("%Y-%m").to_string(),
+                process_name: logs[i].process_name.to_owned(),
+                display_name: logs[i].display_name.to_owned(),
+                seconds: *secs,
+            })
+        })
+        .collect()
+}
+
+/// `profile.json` in the data directory
+pub fn profile_path() -> PathBuf {
+    use_config().data_dir().join(PROFILE_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use crate::tracking::Session;
+
+    use super::*;
+
+    /// Noon of a day in March 2023, UTC
+    fn day(d: u32) -> u64 {
+        1_677_628_800 + (d as u64 - 1) * 86_400 + 12 * 3600
+    }
+
+    fn session(start: u64, secs: u64) -> Session {
+        let mut session = Session::new(start);
+        session.extend(start + secs, secs);
+        session
+    }
+
+    fn app(name: &str, sessions: &[(u64, u64)]) -> TrackLog {
+        let mut log = TrackLog::new("alice", name, name);
+        for (start, secs) in sessions {
+            log.sessions.push(session(*start, *secs));
+        }
+        log
+    }
+
+    #[test]
+    fn earns_achievements_over_all_apps() {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let mut game = app("game", &[(day(2), 3 * 3600)]);
+        game.sessions.push(Session::legacy(100 * 3600));
+        let logs = vec![
+            game,
+            app("editor", &[(day(3), 2 * 3600)]),
+            app("browser", &[(day(4), 2 * 3600)]),
+        ];
+
+        let mut profile = UserProfile::default();
+        let earned = profile.update_in(&logs, TrackingMode::Uptime, day(5), &utc);
+        let ids: Vec<&str> = earned.iter().map(|a| a.id).collect();
+        assert_eq!(ids, vec!["Dedicated", "Balanced"]);
+        assert_eq!(profile.total_secs, 107 * 3600);
+        let favourite = profile.favourite_of("2023-03").unwrap();
+        assert_eq!(
+            (favourite.process_name.as_str(), favourite.seconds),
+            ("game", 3 * 3600)
+        );
+
+        let explorer = profile
+            .progress
+            .iter()
+            .find(|p| p.achievement.id == "Explorer")
+            .unwrap();
+        assert_eq!(explorer.progress.to_string(), "3/5");
+        assert_eq!(explorer.earned_at, None);
+
+        // Earned ones stay, even once the month gets unbalanced
+        let mut logs = logs;
+        logs[1].sessions.push(session(day(6), 20 * 3600));
+        assert!(profile
+            .update_in(&logs, TrackingMode::Uptime, day(7), &utc)
+            .is_empty());
+        assert_eq!(
+            profile.favourite_of("2023-03").unwrap().process_name,
+            "editor"
+        );
+        let balanced = profile
+            .progress
+            .iter()
+            .find(|p| p.achievement.id == "Balanced")
+            .unwrap();
+        assert_eq!(balanced.earned_at, Some(day(5)));
+        assert!(!balanced.progress.is_complete());
+
+        // A new month gets its own favourite
+        profile.update_in(&logs, TrackingMode::Uptime, day(5) + 31 * 86_400, &utc);
+        assert_eq!(profile.favourites.len(), 1);
+        logs[2].sessions.push(session(day(5) + 31 * 86_400, 60));
+        profile.update_in(&logs, TrackingMode::Uptime, day(6) + 31 * 86_400, &utc);
+        let months: Vec<&str> = profile
+            .favourites
+            .iter()
+            .map(|f| f.month.as_str())
+            .collect();
+        assert_eq!(months, vec!["2023-03", "2023-04"]);
+
+        let path =
+            std::env::temp_dir().join(format!("tracker_profile_{}.json", std::process::id()));
+        profile.save(&path).unwrap();
+        let loaded = UserProfile::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.achievements, profile.achievements);
+        assert_eq!(loaded.favourites, profile.favourites);
+    }
+
+    #[test]
+    fn derives_past_favourites_from_history() {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let february = day(1) - 10 * 86_400;
+        let logs = vec![
+            app("game", &[(february, 3600), (day(2), 60)]),
+            app("editor", &[(february, 60), (day(3), 3600)]),
+        ];
+
+        // First update after months of tracking still knows who won them
+        let mut profile = UserProfile::default();
+        profile.update_in(&logs, TrackingMode::Uptime, day(5), &utc);
+        let favourites: Vec<(&str, &str)> = profile
+            .favourites
+            .iter()
+            .map(|f| (f.month.as_str(), f.process_name.as_str()))
+            .collect();
+        assert_eq!(favourites, vec![("2023-02", "game"), ("2023-03", "editor")]);
+
+        // Clients of the daemon get the progress too
+        let sent: UserProfile =
+            serde_json::from_str(&serde_json::to_string(&profile).unwrap()).unwrap();
+        assert_eq!(sent, profile);
+    }
+}
@@ -0,0 +1,252 @@
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::TrackerError;
+use crate::sys::ProcessInfo;
+
+use super::TrackLog;
+
+/* Rules deciding which running processes belong to a tracked app.
+ * An app is running while any of its rules matches any process */
+
+/// Part of the process a rule is checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleField {
+    Name,
+    /// Full path of the executable. First command line argument if the path is unknown
+    Path,
+    /// Command line arguments joined with spaces
+    Cmdline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSyntax {
+    Exact,
+    Glob,
+    /// Matches anywhere in the value unless anchored
+    Regex,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TrackRule {
+    pub field: RuleField,
+    pub syntax: RuleSyntax,
+    pub pattern: String,
+}
+
+impl TrackRule {
+    pub fn new(field: RuleField, syntax: RuleSyntax, pattern: &str) -> Self {
+        Self {
+            field,
+            syntax,
+            pattern: pattern.to_owned(),
+        }
+    }
+
+    /// Rule every app without explicit rules gets
+    pub fn exact_name(name: &str) -> Self {
+        Self::new(RuleField::Name, RuleSyntax::Exact, name)
+    }
+
+    pub fn compile(&self) -> Result<RuleMatcher, TrackerError> {
+        let pattern = match self.syntax {
+            RuleSyntax::Exact => Pattern::Exact(self.pattern.to_owned()),
+            RuleSyntax::Glob => Pattern::Glob(
+                Glob::new(&self.pattern)
+                    .map_err(|e| self.parse_error(e))?
+                    .compile_matcher(),
+            ),
+            RuleSyntax::Regex => {
+                Pattern::Regex(Regex::new(&self.pattern).map_err(|e| self.parse_error(e))?)
+            }
+        };
+        Ok(RuleMatcher {
+            field: self.field,
+            pattern,
+        })
+    }
+
+    fn parse_error(&self, e: impl Display) -> TrackerError {
+        TrackerError::Parse(format!("Invalid rule pattern {}: {}", self.pattern, e))
+    }
+}
+
+enum Pattern {
+    Exact(String),
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+/// Compiled [`TrackRule`]
+pub struct RuleMatcher {
+    field: RuleField,
+    pattern: Pattern,
+}
+
+impl RuleMatcher {
+    pub fn matches(&self, proc: &ProcessInfo) -> bool {
+        let value: Cow<str> = match self.field {
+            RuleField::Name => Cow::Borrowed(&proc.name),
+            RuleField::Path => match proc.exe.as_ref().or(proc.cmdline.first()) {
+                Some(path) => Cow::Borrowed(path),
+                None => return false,
+            },
+            RuleField::Cmdline => Cow::Owned(proc.cmdline.join(" ")),
+        };
+
+        match &self.pattern {
+            Pattern::Exact(pattern) => *pattern == value,
+            Pattern::Glob(glob) => glob.is_match(value.as_ref()),
+            Pattern::Regex(regex) => regex.is_match(&value),
+        }
+    }
+}
+
+/// Pids of live instances per tracked app. Apps with no instance are left out
+pub type LivePids = HashMap<String, BTreeSet<u32>>;
+
+/// Rules of every tracked app, compiled once per check
+pub struct AppMatcher {
+    apps: Vec<(String, Vec<RuleMatcher>)>,
+    /// Why rules were skipped
+    errors: Vec<String>,
+}
+
+impl AppMatcher {
+    /// Invalid rules are skipped and kept in [`AppMatcher::errors`]
+    pub fn new(logs: &[TrackLog]) -> Self {
+        let mut errors = vec![];
+        let apps = logs
+            .iter()
+            .map(|log| {
+                let rules = log
+                    .effective_rules()
+                    .iter()
+                    .filter_map(|rule| match rule.compile() {
+                        Ok(matcher) => Some(matcher),
+                        Err(e) => {
+                            errors.push(format!("Rule of {}: {}", log.process_name, e));
+                            None
+                        }
+                    })
+                    .collect();
+                (log.process_name.to_owned(), rules)
+            })
+            .collect();
+        Self { apps, errors }
+    }
+
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    /// Tracked app the process belongs to. First one wins if rules overlap
+    pub fn app_for(&self, proc: &ProcessInfo) -> Option<String> {
+        self.apps
+            .iter()
+            .find(|(_, rules)| rules.iter().any(|r| r.matches(proc)))
+            .map(|(name, _)| name.to_owned())
+    }
+
+    /// Matching pids of every tracked app that has at least one matching process.
+    /// A process belonging to several apps counts for each of them
+    pub fn running_apps(&self, procs: &[ProcessInfo]) -> LivePids {
+        self.apps
+            .iter()
+            .filter_map(|(name, rules)| {
+                let pids: BTreeSet<u32> = procs
+                    .iter()
+                    .filter(|p| rules.iter().any(|r| r.matches(p)))
+                    .map(|p| p.pid)
+                    .collect();
+                (!pids.is_empty()).then(|| (name.to_owned(), pids))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn procs() -> Vec<ProcessInfo> {
+        vec![
+            ProcessInfo::new("java", 1).with_cmdline(vec![
+                "/usr/lib/jvm/bin/java".to_owned(),
+                "-jar".to_owned(),
+                "/opt/idea/lib/idea.jar".to_owned(),
+            ]),
+            ProcessInfo::new("chrome.exe", 2).with_exe(Some(
+                "C:\\Program Files\\Google\\Chrome\\chrome.exe".to_owned(),
+            )),
+            ProcessInfo::new("vim", 3),
+            ProcessInfo::new("vim", 4),
+        ]
+    }
+
+    fn app(name: &str, rules: Vec<TrackRule>) -> TrackLog {
+        let mut log = TrackLog::new("alice", name, name);
+        log.rules = rules;
+        log
+    }
+
+    #[test]
+    fn matches_by_name_path_and_cmdline() {
+        let logs = vec![
+            app(
+                "idea",
+                vec![TrackRule::new(
+                    RuleField::Cmdline,
+                    RuleSyntax::Regex,
+                    "idea",
+                )],
+            ),
+            app(
+                "chrome",
+                vec![
+                    TrackRule::new(RuleField::Path, RuleSyntax::Glob, "*Chrome*chrome.exe"),
+                    TrackRule::new(RuleField::Name, RuleSyntax::Glob, "chromium*"),
+                ],
+            ),
+            app(
+                "eclipse",
+                vec![TrackRule::new(
+                    RuleField::Cmdline,
+                    RuleSyntax::Regex,
+                    "eclipse",
+                )],
+            ),
+            // Default rule is the exact process name
+            app("vim", vec![]),
+            app("vi", vec![]),
+        ];
+
+        let running = AppMatcher::new(&logs).running_apps(&procs());
+        let expected: LivePids = [("idea", vec![1]), ("chrome", vec![2]), ("vim", vec![3, 4])]
+            .into_iter()
+            .map(|(name, pids)| (name.to_owned(), pids.into_iter().collect()))
+            .collect();
+        assert_eq!(running, expected);
+    }
+
+    #[test]
+    fn skips_invalid_rules() {
+        let logs = vec![app(
+            "game",
+            vec![
+                TrackRule::new(RuleField::Name, RuleSyntax::Regex, "(unclosed"),
+                TrackRule::exact_name("vim"),
+            ],
+        )];
+        let matcher = AppMatcher::new(&logs);
+        assert!(matcher.running_apps(&procs()).contains_key("game"));
+        assert_eq!(matcher.errors().len(), 1);
+    }
+}
@@ -0,0 +1,744 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::error::TrackerError;
+use crate::notify::{NotificationKind, Notifier, ToastSink};
+use crate::storage::json::JsonStorage;
+use crate::storage::sqlite::SqliteStorage;
+use crate::storage::StorageBackend;
+use crate::store::apps_store::{create_apps_store, Actions, AppsStore};
+use crate::store::user_store::use_user_store;
+use crate::sys::clock::{Clock, ManualClock};
+use crate::sys::scripted::ScriptedProcessSource;
+use crate::sys::watcher::ClockNotifier;
+use crate::sys::{ProcessInfo, ProcessNotifier, ProcessSource};
+
+use super::badges::use_badge_defs;
+use super::limits::{LimitLevel, UsageLimits};
+use super::rules::{RuleField, RuleSyntax, TrackRule};
+use super::{start_supervisor_thread, Session, TrackLog, Tracker, TrackingEnv, TrackingMode};
+
+const START: u64 = 1_700_000_000;
+const IDLE_TIMEOUT: u64 = 60;
+
+struct Harness {
+    clock: Arc<ManualClock>,
+    source: Arc<ScriptedProcessSource>,
+    toasts: Arc<ToastSink>,
+    env: TrackingEnv,
+    store: AppsStore,
+}
+
+impl Harness {
+    fn new() -> Self {
+        Self::with_env(|_, _| ())
+    }
+
+    /// Lets a test adjust the environment before the store is created
+    fn with_env(setup: impl FnOnce(&mut TrackingEnv, &Arc<ManualClock>)) -> Self {
+        let clock = Arc::new(ManualClock::new(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(START),
+        ));
+        let source = Arc::new(ScriptedProcessSource::new(clock.clone()));
+        let toasts = Arc::new(ToastSink::default());
+        let mut env = TrackingEnv {
+            source: source.clone(),
+            notifier: Arc::new(ClockNotifier(clock.clone())),
+            focus: source.clone(),
+            idle: source.clone(),
+            idle_timeout: Some(Duration::from_secs(IDLE_TIMEOUT)),
+            instance_sessions: false,
+            notifications: Notifier::new(vec![toasts.clone()]),
+            clock: clock.clone(),
+            storage: Arc::new(SqliteStorage::open_in_memory().unwrap()),
+        };
+        setup(&mut env, &clock);
+        let store = create_apps_store(env.clone());
+
+        Self {
+            clock,
+            source,
+            toasts,
+            env,
+            store,
+        }
+    }
+
+    fn start(&self) {
+        start_supervisor_thread(Tracker::new(self.env.clone(), Arc::downgrade(&self.store)));
+        self.clock.advance(Duration::ZERO);
+    }
+
+    fn dispatch(&self, action: Actions) {
+        self.store.lock().unwrap().dispatch(action);
+        self.clock.advance(Duration::ZERO);
+    }
+
+    fn advance_secs(&self, secs: u64) {
+        self.clock.advance(Duration::from_secs(secs));
+    }
+
+    fn track(&self, proc_name: &str) {
+        self.dispatch(Actions::AddTrackedApp(username(), proc_name.to_owned()));
+    }
+
+    fn log(&self, proc_name: &str) -> TrackLog {
+        self.store
+            .lock()
+            .unwrap()
+            .selector()
+            .tracked_apps
+            .iter()
+            .find(|l| l.process_name == proc_name)
+            .cloned()
+            .expect("App is not tracked")
+    }
+}
+
+fn username() -> String {
+    use_user_store().selector().username.to_owned()
+}
+
+/// Ids of rank badges. Habits depend on the local timezone, ranks don't
+/// Fresh directory for a test, removed with everything in it on drop
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("tracker_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn rank_ids(log: &TrackLog) -> Vec<String> {
+    log.badges
+        .iter()
+        .filter(|b| use_badge_defs().get(&b.id).is_some())
+        .map(|b| b.id.to_owned())
+        .collect()
+}
+
+#[test]
+fn scripted_source_follows_ticks() {
+    let h = Harness::new();
+    h.source
+        .spawn_at(ProcessInfo::new("game", 1), 0, Some(10))
+        .spawn_at(ProcessInfo::new("editor", 2), 5, None);
+    h.start();
+
+    let names = |h: &Harness| -> Vec<String> {
+        h.store
+            .lock()
+            .unwrap()
+            .selector()
+            .untracked_apps
+            .iter()
+            .map(|p| p.name.to_owned())
+            .collect()
+    };
+    assert_eq!(names(&h), vec!["game"]);
+    h.advance_secs(6);
+    assert_eq!(names(&h), vec!["game", "editor"]);
+    h.advance_secs(6);
+    assert_eq!(names(&h), vec!["editor"]);
+}
+
+/// Reports a change every second, like an OS notification arriving mid-interval
+struct EagerNotifier(Arc<ManualClock>);
+
+impl ProcessNotifier for EagerNotifier {
+    fn wait(&self, _timeout: Duration) -> bool {
+        self.0.sleep(Duration::from_secs(1));
+        true
+    }
+}
+
+#[test]
+fn notifications_cut_the_poll_interval_short() {
+    let h = Harness::with_env(|env, clock| env.notifier = Arc::new(EagerNotifier(clock.clone())));
+    h.source
+        .spawn_at(ProcessInfo::new("game", 1), 0, None)
+        .spawn_at(ProcessInfo::new("editor", 2), 1, None);
+    h.start();
+
+    h.advance_secs(2);
+    let names: Vec<String> = h
+        .store
+        .lock()
+        .unwrap()
+        .selector()
+        .untracked_apps
+        .iter()
+        .map(|p| p.name.to_owned())
+        .collect();
+    assert_eq!(names, vec!["game", "editor"]);
+}
+
+#[test]
+fn uptime_accumulates_while_running() {
+    let h = Harness::new();
+    h.source.spawn_at(ProcessInfo::new("game", 1), 0, None);
+    h.start();
+    h.track("game");
+
+    h.advance_secs(30);
+    let log = h.log("game");
+    assert!(log.is_running);
+    assert_eq!(log.uptime(), 30);
+
+    h.advance_secs(30);
+    assert_eq!(h.log("game").uptime(), 60);
+}
+
+#[test]
+fn pauses_on_exit_and_resumes_on_relaunch() {
+    let h = Harness::new();
+    h.source
+        .spawn_at(ProcessInfo::new("game", 1), 0, Some(20))
+        .spawn_at(ProcessInfo::new("game", 2), 40, None);
+    h.start();
+    h.track("game");
+
+    h.advance_secs(30);
+    let log = h.log("game");
+    assert!(!log.is_running);
+    assert_eq!(log.uptime(), 20);
+
+    // Nothing is counted while the app is closed
+    h.advance_secs(10);
+    assert_eq!(h.log("game").uptime(), 20);
+
+    h.advance_secs(20);
+    let log = h.log("game");
+    assert!(log.is_running);
+    assert_eq!(log.uptime(), 35);
+
+    // Each run is kept as its own session
+    assert_eq!(
+        log.sessions,
+        vec![
+            Session {
+                start: START,
+                end: START + 20,
+                duration: 20,
+                focused: 0,
+                legacy: false,
+                pid: None,
+                proc_start: None,
+            },
+            Session {
+                start: START + 45,
+                end: START + 60,
+                duration: 15,
+                focused: 0,
+                legacy: false,
+                pid: None,
+                proc_start: None,
+            },
+        ]
+    );
+    assert_eq!(log.last_session().unwrap().start, START + 45);
+}
+
+#[test]
+fn saves_progress_when_paused() {
+    let h = Harness::new();
+    h.source.spawn_at(ProcessInfo::new("game", 1), 0, Some(20));
+    h.start();
+    h.track("game");
+    h.advance_secs(30);
+
+    let saved = h.env.storage.load_all().unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].process_name, "game");
+    assert_eq!(saved[0].uptime(), 20);
+    assert!(!saved[0].is_running);
+}
+
+#[test]
+fn loads_saved_progress() {
+    let h = Harness::new();
+    let mut log = TrackLog::new(&username(), "game", "Game");
+    let mut session = Session::new(START);
+    session.extend(START + 100, 100);
+    log.update_session(session);
+    h.env.storage.save(&log).unwrap();
+
+    h.start();
+    h.dispatch(Actions::FetchTrackedApps);
+    h.dispatch(Actions::FetchTrackedApps);
+
+    let log = h.log("game");
+    assert_eq!(log.uptime(), 100);
+    assert_eq!(log.display_name, "Game");
+}
+
+#[test]
+fn loads_legacy_uptime_as_single_session() {
+    let dir = TempDir::new("legacy");
+    let path = dir.0.join("stats.json");
+    let legacy = format!(
+        r#"[{{
+            "username": "{}",
+            "uptime": 7200,
+            "badges": [],
+            "process_name": "game",
+            "display_name": "Game",
+            "is_running": false
+        }}]"#,
+        username()
+    );
+    fs::write(&path, legacy).unwrap();
+    let json = JsonStorage::new(&path).with_backups(0, Duration::ZERO);
+
+    let saved = json.load_all().unwrap();
+    assert_eq!(saved[0].uptime(), 7200);
+    assert_eq!(saved[0].sessions, vec![Session::legacy(7200)]);
+    assert!(saved[0].last_session().is_none());
+
+    // Legacy total survives another save
+    json.save(&saved[0]).unwrap();
+    let saved = json.load_all().unwrap();
+    assert_eq!(saved[0].sessions, vec![Session::legacy(7200)]);
+
+    // And is imported into the database as is
+    let db = SqliteStorage::open_in_memory().unwrap();
+    assert_eq!(db.import_legacy_json(&json).unwrap(), 1);
+    assert_eq!(db.import_legacy_json(&json).unwrap(), 0);
+    assert_eq!(
+        db.load_all().unwrap()[0].sessions,
+        vec![Session::legacy(7200)]
+    );
+}
+
+#[test]
+fn awards_badges() {
+    let h = Harness::new();
+    h.source.spawn_at(ProcessInfo::new("game", 1), 0, None);
+    h.start();
+    h.track("game");
+
+    assert_eq!(rank_ids(&h.log("game")), vec!["Initial"]);
+
+    h.advance_secs(3600);
+    assert_eq!(rank_ids(&h.log("game")), vec!["Initial", "Common"]);
+}
+
+#[test]
+fn backfills_missed_badges_on_load() {
+    let mut h = Harness::new();
+    let mut log = TrackLog::new(&username(), "game", "Game");
+    let mut session = Session::new(START - 3 * 3600);
+    session.extend(START, 3 * 3600);
+    log.update_session(session);
+    h.env.storage.save(&log).unwrap();
+
+    let load = |h: &Harness| {
+        h.dispatch(Actions::FetchTrackedApps);
+        h.dispatch(Actions::FetchTrackedApps);
+    };
+    load(&h);
+    let log = h.log("game");
+    assert_eq!(rank_ids(&log), vec!["Initial", "Common", "Rare"]);
+    assert!(log.badges.iter().all(|b| b.earned_at == Some(START)));
+    assert!(h.toasts.recent().is_empty());
+
+    // Saved, and a restart later gives nothing twice
+    h.advance_secs(60);
+    h.store = create_apps_store(h.env.clone());
+    load(&h);
+    let reloaded = h.log("game");
+    assert_eq!(reloaded.badges.len(), log.badges.len());
+    assert!(reloaded.badges.iter().all(|b| b.earned_at == Some(START)));
+}
+
+#[test]
+fn stops_tracking_deleted_app() {
+    let h = Harness::new();
+    h.source.spawn_at(ProcessInfo::new("game", 1), 0, None);
+    h.start();
+    h.track("game");
+    h.advance_secs(150);
+
+    h.dispatch(Actions::DeleteTrackedApp("game".to_owned()));
+    h.advance_secs(10);
+
+    assert!(h.store.lock().unwrap().selector().tracked_apps.is_empty());
+    assert!(h.env.storage.load_all().unwrap().is_empty());
+}
+
+#[test]
+fn reports_restored_stats_file() {
+    let dir = TempDir::new("restored");
+    let path = dir.0.join("stats.json");
+    let json = JsonStorage::new(&path).with_backups(2, Duration::ZERO);
+    json.save(&TrackLog::new(&username(), "game", "Game"))
+        .unwrap();
+    json.save(&TrackLog::new(&username(), "game", "Game"))
+        .unwrap();
+    fs::write(&path, "[{").unwrap();
+
+    let mut h = Harness::new();
+    h.env.storage = Arc::new(json);
+    h.store = create_apps_store(h.env.clone());
+    h.start();
+    h.dispatch(Actions::FetchTrackedApps);
+    h.dispatch(Actions::FetchTrackedApps);
+
+    assert_eq!(h.log("game").display_name, "Game");
+    let error = h.store.lock().unwrap().selector().error.clone();
+    assert!(error.is_some_and(|e| e.contains("restored")));
+
+    h.dispatch(Actions::CleanErrorMsg);
+    assert!(h.store.lock().unwrap().selector().error.is_none());
+}
+
+/// Storage that can't read or write anything
+struct BrokenStorage;
+
+impl StorageBackend for BrokenStorage {
+    fn load_all(&self) -> Result<Vec<TrackLog>, TrackerError> {
+        Err(TrackerError::Storage("disk is gone".to_owned()))
+    }
+
+    fn save(&self, _: &TrackLog) -> Result<(), TrackerError> {
+        self.load_all().map(|_| ())
+    }
+
+    fn delete(&self, _: &TrackLog) -> Result<(), TrackerError> {
+        self.load_all().map(|_| ())
+    }
+}
+
+#[test]
+fn surfaces_storage_errors() {
+    let h = Harness::with_env(|env, _| env.storage = Arc::new(BrokenStorage));
+    let error = |h: &Harness| h.store.lock().unwrap().selector().error.clone();
+    h.start();
+    h.dispatch(Actions::FetchTrackedApps);
+    h.dispatch(Actions::FetchTrackedApps);
+
+    assert!(h.store.lock().unwrap().selector().is_error_tracked);
+    assert_eq!(
+        error(&h).unwrap(),
+        "Couldn't load tracked apps: Storage failed: disk is gone"
+    );
+    h.dispatch(Actions::CleanErrorMsg);
+    assert_eq!(error(&h), None);
+
+    h.track("game");
+    h.dispatch(Actions::SaveAllData);
+    assert!(error(&h).is_some_and(|e| e.starts_with("Cannot save game:")));
+
+    h.dispatch(Actions::SetLimits(
+        "editor".to_owned(),
+        UsageLimits::default(),
+    ));
+    assert_eq!(
+        error(&h).unwrap(),
+        "Cannot set limits of editor: Tracked app editor not found"
+    );
+}
+
+/// Process list that can never be read
+struct BrokenProcessSource;
+
+impl ProcessSource for BrokenProcessSource {
+    fn running_procs(&self) -> Result<Vec<ProcessInfo>, TrackerError> {
+        Err(TrackerError::ProcessAccess("snapshot denied".to_owned()))
+    }
+}
+
+#[test]
+fn reports_process_listing_errors_once() {
+    let h = Harness::with_env(|env, _| env.source = Arc::new(BrokenProcessSource));
+    let error = |h: &Harness| h.store.lock().unwrap().selector().error.clone();
+    h.start();
+    h.advance_secs(3);
+
+    assert_eq!(
+        error(&h).unwrap(),
+        "Couldn't get running processes. Couldn't access processes: snapshot denied"
+    );
+    // Same failure on later checks doesn't bring the dismissed message back
+    h.dispatch(Actions::CleanErrorMsg);
+    h.advance_secs(30);
+    assert_eq!(error(&h), None);
+}
+
+#[test]
+fn tracks_apps_by_rules() {
+    let h = Harness::new();
+    h.source
+        .spawn_at(ProcessInfo::new("chrome", 1), 0, Some(20))
+        .spawn_at(ProcessInfo::new("chrome_helper", 2), 10, Some(40))
+        .spawn_at(
+            ProcessInfo::new("java", 3)
+                .with_cmdline(vec!["java".to_owned(), "idea.jar".to_owned()]),
+            0,
+            None,
+        );
+    h.start();
+    h.track("chrome");
+    h.dispatch(Actions::SetTrackingRules(
+        "chrome".to_owned(),
+        vec![TrackRule::new(RuleField::Name, RuleSyntax::Glob, "chrome*")],
+    ));
+    h.track("idea");
+    h.dispatch(Actions::SetTrackingRules(
+        "idea".to_owned(),
+        vec![TrackRule::new(
+            RuleField::Cmdline,
+            RuleSyntax::Regex,
+            r"idea\.jar",
+        )],
+    ));
+
+    // Helper keeps the app running after the main process is gone
+    h.advance_secs(30);
+    assert!(h.log("chrome").is_running);
+    h.advance_secs(20);
+    let chrome = h.log("chrome");
+    assert!(!chrome.is_running);
+    assert_eq!(chrome.uptime(), 40);
+
+    // Picked up by the supervisor without an exact name match
+    let idea = h.log("idea");
+    assert!(idea.is_running);
+    assert!(idea.uptime() >= 40);
+}
+
+#[test]
+fn counts_focused_time_separately() {
+    let h = Harness::new();
+    h.source
+        .spawn_at(ProcessInfo::new("game", 1), 0, None)
+        .spawn_at(ProcessInfo::new("editor", 2), 0, None)
+        .focus_at(Some(1), 0)
+        .focus_at(Some(2), 30)
+        .focus_at(None, 45);
+    h.start();
+    h.track("game");
+    h.track("editor");
+    h.advance_secs(60);
+
+    let (game, editor) = (h.log("game"), h.log("editor"));
+    assert_eq!((game.uptime(), editor.uptime()), (60, 60));
+    // Only time with focus counts, nothing after the focus is gone
+    assert_eq!(game.time(TrackingMode::Focused), 30);
+    assert_eq!(editor.time(TrackingMode::Focused), 15);
+}
+
+#[test]
+fn leaves_gap_while_user_is_idle() {
+    let h = Harness::new();
+    h.source
+        .spawn_at(ProcessInfo::new("game", 1), 0, None)
+        .away_at(100, Some(400));
+    h.start();
+    h.track("game");
+    h.advance_secs(300);
+
+    // Time since the last input is taken back once the timeout passes
+    let log = h.log("game");
+    assert!(log.is_running);
+    assert_eq!(log.uptime(), 100);
+
+    h.advance_secs(200);
+    let log = h.log("game");
+    assert_eq!(log.sessions.len(), 2);
+    assert_eq!(log.sessions[0].end, START + 100);
+    assert!(log.sessions[1].start >= START + 400);
+    assert_eq!(log.uptime(), 100 + (START + 500 - log.sessions[1].start));
+}
+
+#[test]
+fn keeps_running_while_any_instance_is_alive() {
+    let h = Harness::with_env(|env, _| env.instance_sessions = true);
+    let started = |tick: u64| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(START + tick));
+    h.source
+        .spawn_at(
+            ProcessInfo::new("code", 1).with_start_time(started(0)),
+            0,
+            Some(40),
+        )
+        .spawn_at(
+            ProcessInfo::new("code", 2).with_start_time(started(10)),
+            10,
+            None,
+        );
+    h.start();
+    h.track("code");
+
+    h.advance_secs(60);
+    let log = h.log("code");
+    assert!(log.is_running);
+    assert_eq!(log.uptime(), 60);
+    assert_eq!(log.sessions.len(), 1);
+
+    let instance = |pid: u32| log.instances.iter().find(|s| s.pid == Some(pid)).unwrap();
+    assert_eq!(instance(1).proc_start, Some(START));
+    assert_eq!(instance(2).proc_start, Some(START + 10));
+    // Instances are counted at tracker ticks, every 5 seconds
+    assert_eq!(instance(1).duration, 40);
+    assert_eq!((instance(2).start, instance(2).duration), (START + 15, 45));
+}
+
+#[test]
+fn backfills_time_before_tracker_started() {
+    let h = Harness::new();
+    let launched = SystemTime::UNIX_EPOCH + Duration::from_secs(START - 100);
+    h.source.spawn_at(
+        ProcessInfo::new("game", 1).with_start_time(Some(launched)),
+        0,
+        None,
+    );
+    h.start();
+    h.track("game");
+
+    h.advance_secs(30);
+    let log = h.log("game");
+    assert_eq!(log.sessions.len(), 1);
+    assert_eq!(log.sessions[0].start, START - 100);
+    assert_eq!(log.uptime(), 130);
+}
+
+#[test]
+fn does_not_backfill_while_user_is_away() {
+    let h = Harness::new();
+    let launched = SystemTime::UNIX_EPOCH + Duration::from_secs(START - 100);
+    h.source
+        .spawn_at(
+            ProcessInfo::new("game", 1).with_start_time(Some(launched)),
+            0,
+            None,
+        )
+        .away_at(0, Some(200));
+    h.start();
+    h.advance_secs(100);
+    h.track("game");
+
+    h.advance_secs(50);
+    // Only the time until the last input is filled in
+    let log = h.log("game");
+    assert_eq!(log.sessions.len(), 1);
+    assert_eq!(
+        (log.sessions[0].start, log.sessions[0].end),
+        (START - 100, START)
+    );
+
+    h.advance_secs(100);
+    let log = h.log("game");
+    assert_eq!(log.sessions.len(), 2);
+    assert!(log.sessions[1].start >= START + 200);
+}
+
+#[test]
+fn does_not_count_run_twice_after_restart() {
+    // Previous tracker run saw the game from its launch until 40 seconds ago
+    let mut game = TrackLog::new(&username(), "game", "game");
+    let mut recorded = Session::new(START - 100);
+    recorded.extend(START - 40, 60);
+    game.update_session(recorded);
+    let h = Harness::with_env(|env, _| env.storage.save(&game).unwrap());
+
+    let launched = SystemTime::UNIX_EPOCH + Duration::from_secs(START - 100);
+    h.source.spawn_at(
+        ProcessInfo::new("game", 1).with_start_time(Some(launched)),
+        0,
+        None,
+    );
+    h.start();
+    h.dispatch(Actions::FetchTrackedApps);
+    h.dispatch(Actions::FetchTrackedApps);
+
+    h.advance_secs(30);
+    let log = h.log("game");
+    // Same run continues in the recorded session, which counts every second once
+    assert_eq!(log.sessions.len(), 1);
+    let session = &log.sessions[0];
+    assert_eq!(session.start, START - 100);
+    assert_eq!(session.duration, session.end - session.start);
+}
+
+#[test]
+fn user_pause_holds_until_resumed() {
+    let h = Harness::new();
+    h.source.spawn_at(ProcessInfo::new("game", 1), 0, None);
+    h.start();
+    h.track("game");
+
+    h.advance_secs(20);
+    h.dispatch(Actions::SetPaused("game".to_owned(), true));
+    h.advance_secs(30);
+    let log = h.log("game");
+    assert!(log.paused && !log.is_running);
+    assert_eq!(log.uptime(), 20);
+
+    h.dispatch(Actions::SetPaused("game".to_owned(), false));
+    h.advance_secs(30);
+    let log = h.log("game");
+    assert!(log.is_running);
+    assert_eq!(log.sessions.len(), 2);
+}
+
+#[test]
+fn alerts_once_per_limit_level() {
+    let h = Harness::new();
+    h.source.spawn_at(ProcessInfo::new("game", 1), 0, None);
+    h.start();
+    h.track("game");
+    h.dispatch(Actions::SetLimits(
+        "game".to_owned(),
+        UsageLimits {
+            daily: Some(100),
+            weekly: None,
+        },
+    ));
+    let alerts = |h: &Harness| -> Vec<LimitLevel> {
+        h.store
+            .lock()
+            .unwrap()
+            .selector()
+            .limit_alerts
+            .iter()
+            .map(|a| a.level)
+            .collect()
+    };
+
+    h.advance_secs(60);
+    assert!(alerts(&h).is_empty());
+    h.advance_secs(25);
+    assert_eq!(alerts(&h), vec![LimitLevel::Warning]);
+    h.advance_secs(60);
+    assert_eq!(alerts(&h), vec![LimitLevel::Warning, LimitLevel::Reached]);
+    h.advance_secs(60);
+    assert_eq!(alerts(&h).len(), 2);
+    assert_eq!(h.log("game").limits.daily, Some(100));
+
+    let kinds: Vec<NotificationKind> = h
+        .toasts
+        .recent()
+        .into_iter()
+        .rev()
+        .map(|t| t.notification.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            NotificationKind::Badge,
+            NotificationKind::LimitWarning,
+            NotificationKind::LimitReached
+        ]
+    );
+}
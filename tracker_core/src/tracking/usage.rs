@@ -0,0 +1,348 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, Local, Months, NaiveDate, TimeZone};
+
+use super::{Session, TrackLog, TrackingMode};
+
+/* Usage of tracked apps over calendar periods. Day boundaries follow the given timezone */
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bucket {
+    Day,
+    /// ISO week, starting on Monday
+    Week,
+    Month,
+}
+
+impl Bucket {
+    /// First day of the period the date belongs to
+    pub fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Bucket::Day => date,
+            Bucket::Week => date - Days::new(date.weekday().num_days_from_monday() as u64),
+            Bucket::Month => date.with_day(1).unwrap(),
+        }
+    }
+
+    /// First day of the following period
+    pub fn next_period(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Bucket::Day => start + Days::new(1),
+            Bucket::Week => start + Days::new(7),
+            Bucket::Month => start + Months::new(1),
+        }
+    }
+}
+
+/// Seconds of use within a period. Period ends before `end`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageBucket {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub seconds: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppUsage {
+    pub process_name: String,
+    pub display_name: String,
+    /// Every period touching the range in order, including empty ones
+    pub buckets: Vec<UsageBucket>,
+}
+
+impl AppUsage {
+    pub fn total(&self) -> u64 {
+        self.buckets.iter().map(|b| b.seconds).sum()
+    }
+}
+
+/// Usage per app between `from` and `to` (both inclusive) in the local timezone.
+pub fn usage_by_period(
+    logs: &[TrackLog],
+    bucket: Bucket,
+    from: NaiveDate,
+    to: NaiveDate,
+    mode: TrackingMode,
+) -> Vec<AppUsage> {
+    usage_by_period_in(logs, bucket, from, to, mode, &Local)
+}
+
+/// Same as [`usage_by_period`] for an arbitrary timezone.
+/// Only days inside the range are counted, even if the first or last period extends past it.
+/// Legacy sessions have no dates and are skipped.
+pub fn usage_by_period_in<Tz: TimeZone>(
+    logs: &[TrackLog],
+    bucket: Bucket,
+    from: NaiveDate,
+    to: NaiveDate,
+    mode: TrackingMode,
+    tz: &Tz,
+) -> Vec<AppUsage> {
+    logs.iter()
+        .map(|log| {
+            let mut buckets = BTreeMap::new();
+            let mut start = bucket.period_start(from);
+            while start <= to {
+                buckets.insert(start, 0);
+                start = bucket.next_period(start);
+            }
+
+            for session in &log.sessions {
+                for (day, seconds) in split_by_day(session, mode, tz) {
+                    if day < from || day > to {
+                        continue;
+                    }
+                    if let Some(total) = buckets.get_mut(&bucket.period_start(day)) {
+                        *total += seconds;
+                    }
+                }
+            }
+
+            AppUsage {
+                process_name: log.process_name.to_owned(),
+                display_name: log.display_name.to_owned(),
+                buckets: buckets
+                    .into_iter()
+                    .map(|(start, seconds)| UsageBucket {
+                        start,
+                        end: bucket.next_period(start),
+                        seconds,
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Seconds of the app on every day it was used
+pub fn daily_usage_in<Tz: TimeZone>(
+    log: &TrackLog,
+    mode: TrackingMode,
+    tz: &Tz,
+) -> BTreeMap<NaiveDate, u64> {
+    let mut days: BTreeMap<NaiveDate, u64> = BTreeMap::new();
+    for session in &log.sessions {
+        for (day, seconds) in split_by_day(session, mode, tz) {
+            *days.entry(day).or_default() += seconds;
+        }
+    }
+    days.retain(|_, seconds| *seconds > 0);
+    days
+}
+
+/// Splits seconds counted in `mode` at midnights. They are spread over days proportionally
+/// to wall time, since counted time may be shorter than the time between start and end.
+pub fn split_by_day<Tz: TimeZone>(
+    session: &Session,
+    mode: TrackingMode,
+    tz: &Tz,
+) -> Vec<(NaiveDate, u64)> {
+    let total = session.time(mode);
+    if session.legacy || total == 0 {
+        return vec![];
+    }
+    let start = session.start as i64;
+    let end = (session.end as i64).max(start);
+    let first_day = local_date(start, tz);
+
+    if end == start {
+        return vec![(first_day, total)];
+    }
+
+    let wall = (end - start) as u128;
+    let mut days = vec![];
+    let mut assigned = 0;
+    let mut day = first_day;
+    let mut day_begin = start;
+
+    while day_begin < end {
+        let next_day = day + Days::new(1);
+        let day_end = day_start(next_day, tz).min(end);
+        let seconds = if day_end == end {
+            // Remainder goes to the last day so nothing is lost to rounding
+            total - assigned
+        } else {
+            ((day_end - day_begin) as u128 * total as u128 / wall) as u64
+        };
+        assigned += seconds;
+        days.push((day, seconds));
+
+        day = next_day;
+        day_begin = day_end;
+    }
+    days
+}
+
+fn local_date<Tz: TimeZone>(unix_secs: i64, tz: &Tz) -> NaiveDate {
+    tz.timestamp_opt(unix_secs, 0)
+        .earliest()
+        .map_or(NaiveDate::MIN, |t| t.date_naive())
+}
+
+/// Unix time of local midnight. If midnight is skipped by a DST change, the first valid hour is used
+pub(crate) fn day_start<Tz: TimeZone>(date: NaiveDate, tz: &Tz) -> i64 {
+    (0..24)
+        .filter_map(|hour| {
+            tz.from_local_datetime(&date.and_hms_opt(hour, 0, 0)?)
+                .earliest()
+        })
+        .map(|t| t.timestamp())
+        .next()
+        .unwrap_or_else(|| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// Unix time of a wall clock time in the given timezone
+    fn at(tz: &FixedOffset, d: NaiveDate, hour: u32) -> u64 {
+        tz.from_local_datetime(&d.and_hms_opt(hour, 0, 0).unwrap())
+            .unwrap()
+            .timestamp() as u64
+    }
+
+    fn log_with(sessions: Vec<Session>) -> TrackLog {
+        let mut log = TrackLog::new("user", "game", "Game");
+        log.sessions = sessions;
+        log
+    }
+
+    fn session(start: u64, end: u64, duration: u64) -> Session {
+        let mut s = Session::new(start);
+        s.extend(end, duration);
+        s
+    }
+
+    #[test]
+    fn splits_sessions_at_local_midnight() {
+        let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+        let s = session(
+            at(&tz, date(2024, 3, 4), 23),
+            at(&tz, date(2024, 3, 5), 1),
+            7200,
+        );
+        assert_eq!(
+            split_by_day(&s, TrackingMode::Uptime, &tz),
+            vec![(date(2024, 3, 4), 3600), (date(2024, 3, 5), 3600)]
+        );
+        // Same session is within one day in UTC
+        assert_eq!(
+            split_by_day(&s, TrackingMode::Uptime, &FixedOffset::east_opt(0).unwrap()),
+            vec![(date(2024, 3, 4), 7200)]
+        );
+    }
+
+    #[test]
+    fn buckets_by_day_week_and_month() {
+        let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+        // 2024-03-03 is Sunday, 2024-03-04 is Monday
+        let log = log_with(vec![
+            session(
+                at(&tz, date(2024, 3, 3), 10),
+                at(&tz, date(2024, 3, 3), 11),
+                3600,
+            ),
+            session(
+                at(&tz, date(2024, 3, 4), 10),
+                at(&tz, date(2024, 3, 4), 12),
+                7200,
+            ),
+            session(
+                at(&tz, date(2024, 4, 1), 10),
+                at(&tz, date(2024, 4, 1), 11),
+                1800,
+            ),
+            Session::legacy(100_000),
+        ]);
+        let logs = [log];
+
+        let days = usage_by_period_in(
+            &logs,
+            Bucket::Day,
+            date(2024, 3, 3),
+            date(2024, 3, 5),
+            TrackingMode::Uptime,
+            &tz,
+        );
+        let seconds: Vec<u64> = days[0].buckets.iter().map(|b| b.seconds).collect();
+        assert_eq!(seconds, vec![3600, 7200, 0]);
+
+        let weeks = usage_by_period_in(
+            &logs,
+            Bucket::Week,
+            date(2024, 3, 1),
+            date(2024, 3, 10),
+            TrackingMode::Uptime,
+            &tz,
+        );
+        assert_eq!(weeks[0].buckets[0].start, date(2024, 2, 26));
+        assert_eq!(weeks[0].buckets[0].seconds, 3600);
+        assert_eq!(weeks[0].buckets[1].start, date(2024, 3, 4));
+        assert_eq!(weeks[0].buckets[1].seconds, 7200);
+
+        let months = usage_by_period_in(
+            &logs,
+            Bucket::Month,
+            date(2024, 3, 1),
+            date(2024, 4, 30),
+            TrackingMode::Uptime,
+            &tz,
+        );
+        let seconds: Vec<u64> = months[0].buckets.iter().map(|b| b.seconds).collect();
+        assert_eq!(seconds, vec![10800, 1800]);
+        assert_eq!(months[0].total(), 12600);
+    }
+
+    #[test]
+    fn ignores_days_outside_range() {
+        let tz = FixedOffset::east_opt(0).unwrap();
+        let log = log_with(vec![session(
+            at(&tz, date(2024, 3, 4), 12),
+            at(&tz, date(2024, 3, 6), 12),
+            48 * 3600,
+        )]);
+
+        let usage = usage_by_period_in(
+            &[log],
+            Bucket::Week,
+            date(2024, 3, 5),
+            date(2024, 3, 5),
+            TrackingMode::Uptime,
+            &tz,
+        );
+        assert_eq!(usage[0].buckets.len(), 1);
+        assert_eq!(usage[0].total(), 24 * 3600);
+    }
+
+    #[test]
+    fn reports_focused_time() {
+        let tz = FixedOffset::east_opt(0).unwrap();
+        let mut s = session(
+            at(&tz, date(2024, 3, 4), 22),
+            at(&tz, date(2024, 3, 5), 2),
+            4 * 3600,
+        );
+        s.add_focused(3600);
+
+        assert_eq!(
+            split_by_day(&s, TrackingMode::Focused, &tz),
+            vec![(date(2024, 3, 4), 1800), (date(2024, 3, 5), 1800)]
+        );
+        let usage = usage_by_period_in(
+            &[log_with(vec![s])],
+            Bucket::Week,
+            date(2024, 3, 4),
+            date(2024, 3, 10),
+            TrackingMode::Focused,
+            &tz,
+        );
+        assert_eq!(usage[0].total(), 3600);
+    }
+}
@@ -0,0 +1,46 @@
+use std::process;
+
+use tracker_core::{
+    init_data,
+    ipc::{endpoint, serve, Client},
+    store::apps_store::{use_apps_store, Actions},
+    sys::{hide_console_window, on_termination},
+};
+
+/* Tracks apps without any UI. The GUI and other clients talk to it over the local endpoint */
+
+fn main() {
+    // Logoff, system shutdown or a kill stops the daemon like a client would
+    on_termination(|| {
+        use_apps_store()
+            .lock()
+            .unwrap()
+            .dispatch(Actions::SaveAllData);
+        println!("Tracker daemon stopped");
+        process::exit(0);
+    });
+
+    let endpoint = endpoint();
+    if Client::connect_to(&endpoint).is_ok() {
+        eprintln!(
+            "Tracker daemon is already running on {}",
+            endpoint.display()
+        );
+        process::exit(1);
+    }
+
+    init_data();
+    let shutdown = match serve(use_apps_store(), &endpoint) {
+        Ok(shutdown) => shutdown,
+        Err(e) => {
+            eprintln!("Couldn't listen on {}: {}", endpoint.display(), e);
+            process::exit(1);
+        }
+    };
+    hide_console_window();
+    println!("Listening on {}", endpoint.display());
+
+    // Data is saved before the signal is sent
+    let _ = shutdown.recv();
+    println!("Tracker daemon stopped");
+}